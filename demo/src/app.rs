@@ -44,11 +44,7 @@ impl ConsoleDemo {
         } else {
             Self::default()
         };
-        for cmd in syntax().get_subcommands() {
-            app.console_win
-                .command_table_mut()
-                .push(cmd.get_name().to_string());
-        }
+        egui_console::clap_support::register(&mut app.console_win, &syntax());
 
         app
     }
@@ -69,7 +65,7 @@ impl eframe::App for ConsoleDemo {
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             // The top panel is often a good place for a menu bar:
 
-            egui::menu::bar(ui, |ui| {
+            egui::MenuBar::new().ui(ui, |ui| {
                 // NOTE: no File->Quit on web pages!
                 let is_web = cfg!(target_arch = "wasm32") | true;
                 if !is_web {
@@ -120,13 +116,19 @@ impl eframe::App for ConsoleDemo {
                 if !resp.is_empty() {
                     self.console_win.write(&resp);
                 }
-                self.console_win.prompt();
+                // a background command reprompts when it finishes
+                if !self.console_win.is_busy() {
+                    self.console_win.prompt();
+                }
             }
 
             if ui.button("click for console output").clicked() {
                 self.console_win.write("clicked");
                 self.console_win.prompt();
             }
+            if ui.button("run dir").clicked() {
+                self.console_win.run_command("dir");
+            }
         });
     }
 }
@@ -188,6 +190,31 @@ impl ConsoleDemo {
                 }
                 Ok(result)
             }
+            #[cfg(not(target_arch = "wasm32"))]
+            Some(("stream", _)) => {
+                let session = self.console_win.begin_command();
+                std::thread::spawn(move || {
+                    let sink = session.sink();
+                    for i in 1..=10 {
+                        std::thread::sleep(std::time::Duration::from_millis(200));
+                        sink.println(&format!("line {} from the worker thread", i));
+                    }
+                    session.finish(Ok(()));
+                });
+                Ok("".to_string())
+            }
+            #[cfg(target_arch = "wasm32")]
+            Some(("stream", _)) => Ok("no threads on the web".to_string()),
+            #[cfg(not(target_arch = "wasm32"))]
+            Some(("run", args)) => {
+                let mut words = args.get_many::<String>("program").unwrap();
+                let mut cmd = std::process::Command::new(words.next().unwrap());
+                cmd.args(words);
+                self.console_win.spawn_process(cmd)?;
+                Ok("".to_string())
+            }
+            #[cfg(target_arch = "wasm32")]
+            Some(("run", _)) => Ok("no processes on the web".to_string()),
             Some(("clear_history", _)) => {
                 self.console_win.clear_history();
                 Ok("".to_string())
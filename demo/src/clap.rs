@@ -60,6 +60,24 @@ pub fn syntax() -> Command {
                 .help_template(APPLET_TEMPLATE)
                 .visible_aliases(["clh"]),
         )
+        .subcommand(
+            Command::new("stream")
+                .about("stream output from a background thread")
+                .help_template(APPLET_TEMPLATE),
+        )
+        .subcommand(
+            Command::new("run")
+                .about("run a program, Ctrl-C kills it")
+                .arg(
+                    Arg::new("program")
+                        .required(true)
+                        .num_args(1..)
+                        .trailing_var_arg(true)
+                        .allow_hyphen_values(true),
+                )
+                .arg_required_else_help(true)
+                .help_template(APPLET_TEMPLATE),
+        )
         .subcommand(
             Command::new("cd")
                 .about("change current dir")
@@ -0,0 +1,116 @@
+use crate::ConsoleWindow;
+use egui::Ui;
+
+// the most text kept for one announcement, a burst of output is cut from the front
+const ANNOUNCE_LIMIT: usize = 4000;
+
+/// Output written since the last frame, for screen readers
+#[derive(Debug, Default)]
+pub(crate) struct Announcements {
+    output: String,
+    errors: String,
+    // what the live regions say until something new is written
+    shown_output: String,
+    shown_errors: String,
+}
+
+impl ConsoleWindow {
+    // remember a written line so only the new lines are read out
+    pub(crate) fn announce(&mut self, line: &str, error: bool) {
+        if !cfg!(feature = "accesskit") || self.accessibility_off {
+            return;
+        }
+        let pending = match error {
+            true => &mut self.announcements.errors,
+            false => &mut self.announcements.output,
+        };
+        if !pending.is_empty() {
+            pending.push('\n');
+        }
+        pending.push_str(line);
+        if pending.len() > ANNOUNCE_LIMIT {
+            let mut cut = pending.len() - ANNOUNCE_LIMIT;
+            while !pending.is_char_boundary(cut) {
+                cut += 1;
+            }
+            pending.drain(..cut);
+        }
+    }
+
+    // the output as a polite live log, errors as an assertive alert and the input labelled
+    // with the prompt
+    pub(crate) fn accessibility_ui(&mut self, ui: &Ui) {
+        if self.accessibility_off {
+            return;
+        }
+        let id = self.id();
+        let a = &mut self.announcements;
+        if !a.output.is_empty() {
+            a.shown_output = std::mem::take(&mut a.output);
+        }
+        if !a.errors.is_empty() {
+            a.shown_errors = std::mem::take(&mut a.errors);
+        }
+        #[cfg(feature = "accesskit")]
+        {
+            use egui::accesskit::{Live, Role};
+            let ctx = ui.ctx();
+            let prompt = self.prompt.trim().to_string();
+            ctx.accesskit_node_builder(id, |node| node.set_label(prompt));
+            let a = &self.announcements;
+            let regions = [
+                ("output", Role::Log, Live::Polite, &a.shown_output),
+                ("errors", Role::Alert, Live::Assertive, &a.shown_errors),
+            ];
+            for (name, role, live, text) in regions {
+                ctx.accesskit_node_builder(id.with(name), |node| {
+                    node.set_role(role);
+                    node.set_live(live);
+                    node.set_read_only();
+                    node.set_label(format!("console {}", name));
+                    node.set_value(text.as_str());
+                });
+            }
+        }
+        #[cfg(not(feature = "accesskit"))]
+        let _ = (ui, id);
+    }
+}
+
+#[cfg(feature = "accesskit")]
+#[test]
+fn test_announce() {
+    use egui::accesskit::Role;
+    let ctx = egui::Context::default();
+    ctx.enable_accesskit();
+    let mut console = crate::ConsoleBuilder::new().build();
+    let frame = |console: &mut ConsoleWindow| {
+        let output = ctx.run(Default::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                console.draw(ui);
+            });
+        });
+        let update = output.platform_output.accesskit_update.unwrap();
+        let value = |role| {
+            update
+                .nodes
+                .iter()
+                .find(|(_, node)| node.role() == role)
+                .and_then(|(_, node)| node.value().map(str::to_string))
+        };
+        (value(Role::Log), value(Role::Alert))
+    };
+    console.write("first");
+    assert_eq!(frame(&mut console).0.as_deref(), Some("first"));
+    // only the new lines are read out
+    console.write("second");
+    console.write("third");
+    console.sink().write_error("oops");
+    let (log, alert) = frame(&mut console);
+    assert_eq!(log.as_deref(), Some("second\nthird"));
+    assert_eq!(alert.as_deref(), Some("oops"));
+
+    let mut quiet = crate::ConsoleBuilder::new().accessibility(false).build();
+    quiet.write("first");
+    assert_eq!(frame(&mut quiet), (None, None));
+}
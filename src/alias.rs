@@ -0,0 +1,146 @@
+use crate::ConsoleWindow;
+use std::collections::BTreeMap;
+
+// how many times a line can be expanded, stops aliases that expand to each other
+const MAX_ALIAS_DEPTH: usize = 8;
+
+impl ConsoleWindow {
+    /// Define an alias
+    /// # Arguments
+    /// * `name` - the alias, matched against the first word of an entered line
+    /// * `expansion` - what the first word is replaced with
+    ///
+    /// The expansion happens before a registered command is looked up or the line is
+    /// returned to the host. The history keeps what the user typed. An alias can expand
+    /// to another alias, but never back to one already expanded.
+    ///
+    /// The name is added to the tab completion table.
+    ///
+    pub fn set_alias(&mut self, name: &str, expansion: &str) {
+        self.aliases.insert(name.to_string(), expansion.to_string());
+        if !self.tab_command_table.iter().any(|c| c == name) {
+            self.tab_command_table.push(name.to_string());
+        }
+    }
+
+    /// Remove an alias
+    /// # Returns
+    /// * `Option<String>` - the expansion it had
+    ///
+    pub fn remove_alias(&mut self, name: &str) -> Option<String> {
+        let expansion = self.aliases.remove(name)?;
+        if !self.command_uses_name(name) {
+            self.tab_command_table.retain(|c| c != name);
+        }
+        Some(expansion)
+    }
+
+    /// Get the aliases
+    /// # Returns
+    /// * `&BTreeMap<String, String>` - alias names and their expansions
+    ///
+    pub fn aliases(&self) -> &BTreeMap<String, String> {
+        &self.aliases
+    }
+
+    /// Register the `alias` and `unalias` commands
+    ///
+    /// - `alias` lists the aliases
+    /// - `alias name` shows one alias
+    /// - `alias name='expansion'` defines one
+    /// - `unalias name` removes one
+    ///
+    /// With the persistence feature the aliases are saved along with the history.
+    ///
+    pub fn register_alias_commands(&mut self) {
+        self.register_command("alias", &[], "define or list aliases", |console, args| {
+            if args.len() < 2 {
+                let list = console
+                    .aliases
+                    .iter()
+                    .map(|(name, expansion)| format!("alias {}='{}'", name, expansion))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                if !list.is_empty() {
+                    console.write(&list);
+                }
+                return Ok(());
+            }
+            for arg in &args[1..] {
+                match arg.split_once('=') {
+                    Some((name, expansion)) if !name.is_empty() => {
+                        console.set_alias(name, expansion)
+                    }
+                    Some(_) => return Err(format!("alias: bad alias '{}'", arg)),
+                    None => {
+                        let expansion = console
+                            .aliases
+                            .get(arg)
+                            .ok_or_else(|| format!("alias: {} not found", arg))?;
+                        let line = format!("alias {}='{}'", arg, expansion);
+                        console.write(&line);
+                    }
+                }
+            }
+            Ok(())
+        });
+        self.register_command("unalias", &[], "remove aliases", |console, args| {
+            if args.len() < 2 {
+                return Err("usage: unalias name ...".to_string());
+            }
+            for name in &args[1..] {
+                console
+                    .remove_alias(name)
+                    .ok_or_else(|| format!("unalias: {} not found", name))?;
+            }
+            Ok(())
+        });
+    }
+
+    // replace the first word of the line while it is an alias
+    pub(crate) fn expand_alias(&self, line: &str) -> String {
+        let mut line = line.to_string();
+        let mut seen: Vec<&str> = Vec::new();
+        while seen.len() < MAX_ALIAS_DEPTH {
+            let trimmed = line.trim_start();
+            let word_end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+            let word = &trimmed[..word_end];
+            let Some((name, expansion)) = self.aliases.get_key_value(word) else {
+                break;
+            };
+            if seen.contains(&name.as_str()) {
+                break;
+            }
+            seen.push(name);
+            line = format!("{}{}", expansion, &trimmed[word_end..]);
+        }
+        line
+    }
+}
+
+#[test]
+fn test_expand_alias() {
+    let mut console = crate::ConsoleBuilder::new().build();
+    console.set_alias("ll", "dir -l");
+    console.set_alias("dir", "ls");
+    console.set_alias("ls", "ls -a");
+    console.set_alias("a", "b");
+    console.set_alias("b", "a");
+    assert_eq!(console.expand_alias("ll foo"), "ls -a -l foo");
+    assert_eq!(console.expand_alias("  dir"), "ls -a");
+    assert_eq!(console.expand_alias("lll"), "lll");
+    assert_eq!(console.expand_alias("a x"), "a x");
+    assert!(console.tab_command_table.contains(&"ll".to_string()));
+
+    console.register_alias_commands();
+    console.run_registered("unalias ll".to_string());
+    assert!(!console.aliases().contains_key("ll"));
+    assert!(!console.tab_command_table.contains(&"ll".to_string()));
+    // a command alias of the same name still completes
+    console.register_command("greet", &["hi"], "say hello", |_, _| Ok(()));
+    console.set_alias("hi", "greet world");
+    console.run_registered("unalias hi".to_string());
+    assert!(console.tab_command_table.contains(&"hi".to_string()));
+    console.run_registered("alias g='greet world'".to_string());
+    assert_eq!(console.aliases()["g"], "greet world");
+}
@@ -0,0 +1,191 @@
+use crate::{ConsoleEvent, ConsoleWindow};
+
+/// One piece of a chained line
+#[derive(Debug)]
+pub(crate) struct ChainedCommand {
+    // joined to the previous piece with && rather than ;
+    needs_success: bool,
+    line: String,
+}
+
+impl ConsoleWindow {
+    /// Report the result of the last command
+    /// # Arguments
+    /// * `success` - did the command succeed
+    ///
+    /// This also ends an output redirection, see [`crate::ConsoleBuilder::redirect_output`].
+    ///
+    /// Needed with [`crate::ConsoleBuilder::chain_commands`]. A piece of a chained
+    /// line that follows `&&` is only returned once the previous piece was reported as a
+    /// success. A failure skips it. Registered commands and command sessions report their
+    /// own result.
+    ///
+    pub fn command_finished(&mut self, success: bool) {
+        self.end_redirect();
+        self.set_last_status(if success { Ok(()) } else { Err(1) });
+    }
+
+    /// Record the exit status of the last command
+    /// # Arguments
+    /// * `status` - `Ok` for success or the error code
+    ///
+    /// Like [`ConsoleWindow::command_finished`] but keeps the code, which is what `$?`
+    /// expands to. A failed registered command or command session records `Err(1)`.
+    ///
+    pub fn set_last_status(&mut self, status: Result<(), i32>) {
+        self.last_status = status.err();
+        self.chain_status = Some(status.is_ok());
+    }
+
+    /// Get the exit status of the last command
+    /// # Returns
+    /// * `Result<(), i32>` - `Ok` if it succeeded or nothing has been recorded yet
+    ///
+    pub fn last_status(&self) -> Result<(), i32> {
+        self.last_status.map_or(Ok(()), Err)
+    }
+
+    // an entered line, split into pieces if chaining is on
+    pub(crate) fn run_line(&mut self, line: String) -> ConsoleEvent {
+        if !self.chain_commands {
+            return self.run_registered(line);
+        }
+        let mut pieces = split_chain(&line);
+        if pieces.len() < 2 {
+            return self.run_registered(line);
+        }
+        let first = pieces.remove(0);
+        self.chain = pieces.into();
+        self.run_piece(first)
+    }
+
+    // the next piece of a chained line, once the previous one is done
+    pub(crate) fn next_chained(&mut self) -> ConsoleEvent {
+        if self.is_busy() {
+            return ConsoleEvent::None;
+        }
+        while let Some(next) = self.chain.front() {
+            match (next.needs_success, self.chain_status) {
+                (true, None) => return ConsoleEvent::None,
+                // skipped, the failure carries on to the next &&
+                (true, Some(false)) => {
+                    self.chain.pop_front();
+                }
+                _ => {
+                    let next = self.chain.pop_front().unwrap();
+                    return self.run_piece(next);
+                }
+            }
+        }
+        // the host tried to prompt while pieces were waiting
+        if std::mem::take(&mut self.prompt_deferred) {
+            self.draw_prompt();
+        }
+        ConsoleEvent::None
+    }
+
+    // drop the rest of a chained line
+    pub(crate) fn cancel_chain(&mut self) {
+        self.chain.clear();
+        self.prompt_deferred = false;
+    }
+
+    fn run_piece(&mut self, piece: ChainedCommand) -> ConsoleEvent {
+        self.chain_status = None;
+        self.prompt_deferred = false;
+        self.run_registered(piece.line)
+    }
+}
+
+// split a line on ; and && outside of quotes, empty pieces are dropped
+pub(crate) fn split_chain(line: &str) -> Vec<ChainedCommand> {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    let mut needs_success = false;
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars().peekable();
+
+    let mut push = |current: &mut String, needs_success: bool| {
+        let piece = current.trim();
+        if !piece.is_empty() {
+            pieces.push(ChainedCommand {
+                needs_success,
+                line: piece.to_string(),
+            });
+        }
+        current.clear();
+    };
+
+    while let Some(ch) = chars.next() {
+        match quote {
+            Some(qc) if ch == qc => quote = None,
+            Some('"') if ch == '\\' => {
+                current.push(ch);
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+                continue;
+            }
+            Some(_) => {}
+            None => match ch {
+                '"' | '\'' => quote = Some(ch),
+                ';' => {
+                    push(&mut current, needs_success);
+                    needs_success = false;
+                    continue;
+                }
+                '&' if chars.peek() == Some(&'&') => {
+                    chars.next();
+                    push(&mut current, needs_success);
+                    needs_success = true;
+                    continue;
+                }
+                _ => {}
+            },
+        }
+        current.push(ch);
+    }
+    push(&mut current, needs_success);
+    pieces
+}
+
+#[test]
+fn test_split_chain() {
+    let pieces: Vec<(bool, String)> = split_chain("cd demo && dir ; echo 'a;b' \"&&\" && x&y;")
+        .into_iter()
+        .map(|p| (p.needs_success, p.line))
+        .collect();
+    assert_eq!(
+        pieces,
+        vec![
+            (false, "cd demo".to_string()),
+            (true, "dir".to_string()),
+            (false, "echo 'a;b' \"&&\"".to_string()),
+            (true, "x&y".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_chain() {
+    let mut console = crate::ConsoleBuilder::new().chain_commands(true).build();
+    console.prompt();
+    let cmd = |line: &str| ConsoleEvent::Command(line.to_string());
+
+    assert_eq!(console.run_line("a && b; c && d".to_string()), cmd("a"));
+    // b waits for a to be reported
+    assert_eq!(console.next_chained(), ConsoleEvent::None);
+    console.prompt();
+    console.command_finished(true);
+    assert_eq!(console.next_chained(), cmd("b"));
+    console.prompt();
+    console.command_finished(false);
+    // c runs whatever happened to b, d is skipped
+    assert_eq!(console.next_chained(), cmd("c"));
+    console.command_finished(false);
+    console.prompt();
+    assert_eq!(console.next_chained(), ConsoleEvent::None);
+    assert!(console.chain.is_empty());
+    // the prompts held back while pieces were waiting become one
+    assert_eq!(console.text, ">> \n>> ");
+}
@@ -0,0 +1,74 @@
+//! Drive the console from a [`clap::Command`] model
+//!
+//! ```ignore
+//! let syntax = clap::Command::new("app")
+//!     .multicall(true)
+//!     .subcommand(clap::Command::new("quit").visible_alias("q"));
+//! egui_console::clap_support::register(&mut console, &syntax);
+//! ```
+use crate::ConsoleWindow;
+
+/// Register the subcommands of a clap model with the console
+/// # Arguments
+/// * `console` - the console
+/// * `cmd` - the clap model, its subcommands are the console commands
+///
+/// The subcommand names and their visible aliases are added to the tab completion table.
+/// A `help` command is registered that writes the help generated by clap, `help <command>`
+/// writes the help of one command.
+///
+/// Call it again when the model changes, names added by the previous call are replaced.
+///
+pub fn register(console: &mut ConsoleWindow, cmd: &clap::Command) {
+    let mut model = cmd.clone();
+    model.build();
+    console.register_command(
+        "help",
+        &[],
+        "show help, help <command> for one command",
+        move |console, args| {
+            let help = match args.get(1) {
+                Some(name) => model
+                    .find_subcommand_mut(name)
+                    .ok_or_else(|| format!("no such command '{}'", name))?
+                    .render_help(),
+                None => model.render_help(),
+            };
+            console.write(help.to_string().trim_end());
+            Ok(())
+        },
+    );
+
+    let names: Vec<String> = cmd
+        .get_subcommands()
+        .flat_map(|sub| std::iter::once(sub.get_name()).chain(sub.get_visible_aliases()))
+        .map(str::to_string)
+        .collect();
+    let old_names = std::mem::take(&mut console.clap_names);
+    console
+        .tab_command_table
+        .retain(|c| !old_names.contains(c) && !names.contains(c));
+    console.tab_command_table.extend(names.iter().cloned());
+    console.clap_names = names;
+}
+
+#[test]
+fn test_register() {
+    let mut console = crate::ConsoleBuilder::new().build();
+    let model = clap::Command::new("app")
+        .multicall(true)
+        .subcommand(clap::Command::new("quit").visible_alias("q").about("leave"))
+        .subcommand(clap::Command::new("dir").about("list files"));
+    register(&mut console, &model);
+    assert_eq!(console.tab_command_table, vec!["help", "quit", "q", "dir"]);
+
+    // re-registering a changed model replaces the names
+    let model = model.mut_subcommand("dir", |dir| dir.name("ls"));
+    register(&mut console, &model);
+    assert_eq!(console.tab_command_table, vec!["help", "quit", "q", "ls"]);
+
+    console.run_registered("help quit".to_string());
+    assert!(console.text.contains("leave"));
+    console.run_registered("help nope".to_string());
+    assert!(console.text.contains("no such command 'nope'"));
+}
@@ -0,0 +1,518 @@
+use crate::{ConsoleEvent, ConsoleWindow};
+use egui::{Rect, Ui};
+use std::ops::Range;
+
+type Handler = Box<dyn FnMut(&mut ConsoleWindow, &[String]) -> Result<(), String>>;
+
+/// A command registered with [`ConsoleWindow::register_command`]
+pub(crate) struct RegisteredCommand {
+    pub(crate) name: String,
+    pub(crate) aliases: Vec<String>,
+    pub(crate) description: String,
+    // the argument signature, see ConsoleWindow::set_command_args
+    pub(crate) args: Vec<String>,
+    // taken out while the handler is running
+    handler: Option<Handler>,
+}
+
+impl std::fmt::Debug for RegisteredCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RegisteredCommand")
+            .field("name", &self.name)
+            .field("aliases", &self.aliases)
+            .field("description", &self.description)
+            .field("args", &self.args)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RegisteredCommand {
+    fn matches(&self, name: &str) -> bool {
+        self.name == name || self.aliases.iter().any(|a| a == name)
+    }
+}
+
+impl ConsoleWindow {
+    /// Register a command that the console runs itself
+    /// # Arguments
+    /// * `name` - the command name, the first word of the line
+    /// * `aliases` - other names for the same command
+    /// * `description` - one line of help text
+    /// * `handler` - called with the console and the split line
+    ///
+    /// When the user enters a line whose first word is `name` or one of the `aliases`
+    /// the handler is called instead of returning [`ConsoleEvent::Command`]. The line is
+    /// split into words honoring quotes, the first word being the command name itself.
+    /// If the handler returns an error it is written to the console. The console is
+    /// reprompted either way.
+    ///
+    /// The name and aliases are added to the tab completion table.
+    ///
+    /// Registering a name again replaces the earlier command.
+    ///
+    pub fn register_command(
+        &mut self,
+        name: &str,
+        aliases: &[&str],
+        description: &str,
+        handler: impl FnMut(&mut ConsoleWindow, &[String]) -> Result<(), String> + 'static,
+    ) {
+        // the old aliases go from tab completion too
+        self.retain_commands(|cmd| cmd != name);
+        for cmd in std::iter::once(&name).chain(aliases) {
+            if !self.tab_command_table.iter().any(|c| c == cmd) {
+                self.tab_command_table.push(cmd.to_string());
+            }
+        }
+        self.commands.push(RegisteredCommand {
+            name: name.to_string(),
+            aliases: aliases.iter().map(|a| a.to_string()).collect(),
+            description: description.to_string(),
+            args: Vec::new(),
+            handler: Some(Box::new(handler)),
+        });
+    }
+
+    /// Remove a registered command
+    /// # Arguments
+    /// * `name` - the command name, an alias does not remove it
+    ///
+    /// # Returns
+    /// * `bool` - true if there was such a command
+    ///
+    /// Its name and aliases leave the tab completion table unless another command or a
+    /// console alias still uses them.
+    ///
+    pub fn unregister_command(&mut self, name: &str) -> bool {
+        let before = self.commands.len();
+        self.retain_commands(|cmd| cmd != name);
+        self.commands.len() != before
+    }
+
+    /// Remove all registered commands
+    ///
+    pub fn clear_commands(&mut self) {
+        self.retain_commands(|_| false);
+    }
+
+    /// Keep only the registered commands whose name passes the test
+    /// # Arguments
+    /// * `keep` - called with each command name, false removes the command
+    ///
+    /// A handler can remove its own command, it is dropped once it returns.
+    ///
+    pub fn retain_commands(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        let (kept, removed): (Vec<_>, Vec<_>) = std::mem::take(&mut self.commands)
+            .into_iter()
+            .partition(|cmd| keep(&cmd.name));
+        self.commands = kept;
+        for cmd in removed {
+            for name in std::iter::once(&cmd.name).chain(&cmd.aliases) {
+                if !self.command_uses_name(name) && !self.aliases.contains_key(name) {
+                    self.tab_command_table.retain(|c| c != name);
+                }
+            }
+        }
+    }
+
+    // whether a registered command or the clap model still answers to the name
+    pub(crate) fn command_uses_name(&self, name: &str) -> bool {
+        #[cfg(feature = "clap")]
+        if self.clap_names.iter().any(|c| c == name) {
+            return true;
+        }
+        self.commands.iter().any(|c| c.matches(name))
+    }
+
+    /// Describe the arguments of a registered command
+    /// # Arguments
+    /// * `name` - the command name
+    /// * `args` - one entry per argument, like `<src>` or `[dest]`, a last one ending in
+    ///   `...` takes the rest of the line
+    ///
+    /// # Returns
+    /// * `bool` - false if there is no such command
+    ///
+    /// The inline help shows them after the command name and highlights the one being
+    /// typed, see [`crate::ConsoleBuilder::inline_help`]. Registering the name again
+    /// clears them.
+    ///
+    pub fn set_command_args(&mut self, name: &str, args: &[&str]) -> bool {
+        match self.commands.iter_mut().find(|c| c.name == name) {
+            Some(cmd) => {
+                cmd.args = args.iter().map(|a| a.to_string()).collect();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Register a `help` command that lists the registered commands
+    ///
+    pub fn register_help_command(&mut self) {
+        self.register_command("help", &[], "list the available commands", |console, _| {
+            let help = console.command_help();
+            console.write(&help);
+            Ok(())
+        });
+    }
+
+    /// Get the help text for the registered commands
+    /// # Returns
+    /// * `String` - one line per command with its aliases and description
+    ///
+    pub fn command_help(&self) -> String {
+        let mut commands: Vec<&RegisteredCommand> = self.commands.iter().collect();
+        commands.sort_by(|a, b| a.name.cmp(&b.name));
+        let names: Vec<String> = commands
+            .iter()
+            .map(|c| {
+                if c.aliases.is_empty() {
+                    c.name.clone()
+                } else {
+                    format!("{} ({})", c.name, c.aliases.join(", "))
+                }
+            })
+            .collect();
+        let width = names.iter().map(|n| n.chars().count()).max().unwrap_or(0);
+        names
+            .iter()
+            .zip(commands)
+            .map(|(name, c)| format!("{:width$}  {}", name, c.description))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // the hint for the registered command being typed, see ConsoleBuilder::inline_help,
+    // with the byte range of the argument the cursor is in, the end of the line if no
+    // cursor is given
+    pub(crate) fn inline_help_text(
+        &self,
+        cursor: Option<usize>,
+    ) -> Option<(String, Option<Range<usize>>)> {
+        // a held paste asks its question in the same place
+        if !self.inline_help || self.search_partial.is_some() || self.pending_paste.is_some() {
+            return None;
+        }
+        let line = self.get_last_line();
+        let name = line.split_whitespace().next()?;
+        let cmd = self.commands.iter().find(|c| c.matches(name))?;
+
+        let mut help = cmd.name.clone();
+        if !cmd.aliases.is_empty() {
+            help.push_str(&format!(" ({})", cmd.aliases.join(", ")));
+        }
+        let mut highlight = None;
+        if let Some(current) = self.current_arg(line, cursor) {
+            let last = cmd.args.len().saturating_sub(1);
+            let rest = cmd.args.last().is_some_and(|a| a.ends_with("..."));
+            let current = if rest { current.min(last) } else { current };
+            for (i, arg) in cmd.args.iter().enumerate() {
+                help.push(' ');
+                if i == current {
+                    highlight = Some(help.len()..help.len() + arg.len());
+                }
+                help.push_str(arg);
+            }
+        } else {
+            for arg in &cmd.args {
+                help.push(' ');
+                help.push_str(arg);
+            }
+        }
+        help.push_str("  ");
+        help.push_str(&cmd.description);
+        Some((help, highlight))
+    }
+
+    // which argument the cursor is in, none while it is on the command name, quotes are
+    // not taken into account
+    fn current_arg(&self, line: &str, cursor: Option<usize>) -> Option<usize> {
+        let typed = match cursor {
+            Some(cursor) => {
+                let line_start = self.text.chars().count() - line.chars().count();
+                let len = cursor.checked_sub(line_start)?;
+                line.char_indices()
+                    .nth(len)
+                    .map_or(line, |(i, _)| &line[..i])
+            }
+            None => line,
+        };
+        let words = typed.split_whitespace().count();
+        match typed.ends_with(char::is_whitespace) {
+            true => words.checked_sub(1),
+            false => words.checked_sub(2),
+        }
+    }
+
+    // draw the hint just above the input line
+    pub(crate) fn inline_help_ui(&self, ui: &Ui, rect: Rect) {
+        let cursor = egui::TextEdit::load_state(ui.ctx(), self.id())
+            .and_then(|state| state.cursor.char_range())
+            .map(|range| range.primary.index);
+        if let Some((help, highlight)) = self.inline_help_text(cursor) {
+            self.input_hint_ui(ui, rect, "inline_help", &help, highlight);
+        }
+    }
+
+    // run the line if it is a registered command, otherwise hand it to the host
+    pub(crate) fn run_registered(&mut self, line: String) -> ConsoleEvent {
+        let line = match self.expand_vars {
+            true => self.expand_vars(&line),
+            false => line,
+        };
+        let line = match self.start_redirect(line) {
+            Ok(line) => line,
+            Err(err) => {
+                self.set_last_status(Err(1));
+                self.write_error_display(&err);
+                self.prompt();
+                return ConsoleEvent::None;
+            }
+        };
+        let line = self.expand_alias(&line);
+        let args = match split_args(&line) {
+            Ok(args) => args,
+            Err(err) if self.parse_commands => {
+                self.set_last_status(Err(1));
+                self.write_error_display(&err);
+                self.prompt();
+                return ConsoleEvent::None;
+            }
+            Err(_) => return ConsoleEvent::Command(line),
+        };
+        let Some(cmd) = args
+            .first()
+            .and_then(|name| self.commands.iter_mut().find(|c| c.matches(name)))
+        else {
+            return self.host_command(line, args);
+        };
+        // a handler that is already running is not re-entered
+        let Some(mut handler) = cmd.handler.take() else {
+            return self.host_command(line, args);
+        };
+        let cmd_name = cmd.name.clone();
+
+        let result = handler(self, &args);
+
+        // the handler might have replaced itself
+        if let Some(cmd) = self.commands.iter_mut().find(|c| c.name == cmd_name) {
+            cmd.handler.get_or_insert(handler);
+        }
+        self.set_last_status(result.as_ref().map(|_| ()).map_err(|_| 1));
+        if let Err(err) = result {
+            self.write_error_display(&err);
+        }
+        self.prompt();
+        ConsoleEvent::None
+    }
+
+    fn host_command(&self, raw: String, args: Vec<String>) -> ConsoleEvent {
+        if self.parse_commands {
+            ConsoleEvent::CommandArgs { raw, args }
+        } else {
+            ConsoleEvent::Command(raw)
+        }
+    }
+}
+
+// split a line into words, quotes group words and are removed
+pub(crate) fn split_args(line: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars();
+
+    while let Some(ch) = chars.next() {
+        match quote {
+            Some(qc) if ch == qc => quote = None,
+            // backslash escapes only mean something inside double quotes
+            Some('"') if ch == '\\' => match chars.next() {
+                Some(next) => word.push(next),
+                None => return Err("trailing backslash".to_string()),
+            },
+            Some(_) => word.push(ch),
+            None => match ch {
+                '"' | '\'' => {
+                    quote = Some(ch);
+                    in_word = true;
+                }
+                ' ' | '\t' => {
+                    if in_word {
+                        args.push(std::mem::take(&mut word));
+                        in_word = false;
+                    }
+                }
+                _ => {
+                    word.push(ch);
+                    in_word = true;
+                }
+            },
+        }
+    }
+    if let Some(qc) = quote {
+        return Err(format!("unbalanced {} quote", qc));
+    }
+    if in_word {
+        args.push(word);
+    }
+    Ok(args)
+}
+
+#[test]
+fn test_split_args() {
+    assert_eq!(split_args("cd foo").unwrap(), vec!["cd", "foo"]);
+    assert_eq!(split_args("  cd   foo  ").unwrap(), vec!["cd", "foo"]);
+    assert_eq!(split_args("cd \"foo bar\"").unwrap(), vec!["cd", "foo bar"]);
+    assert_eq!(split_args("cd 'foo bar'").unwrap(), vec!["cd", "foo bar"]);
+    assert_eq!(
+        split_args("say \"a \\\"b\\\"\"").unwrap(),
+        vec!["say", "a \"b\""]
+    );
+    assert_eq!(split_args("say 'a\\b'").unwrap(), vec!["say", "a\\b"]);
+    assert_eq!(split_args("x \"\"").unwrap(), vec!["x", ""]);
+    assert_eq!(split_args("").unwrap(), Vec::<String>::new());
+    assert!(split_args("cd \"foo").is_err());
+    assert!(split_args("cd 'foo").is_err());
+}
+
+#[test]
+fn test_registered_command() {
+    let mut console = crate::ConsoleBuilder::new().build();
+    console.register_command("greet", &["hi"], "say hello", |console, args| {
+        console.write(&format!("hello {}", args[1..].join(" ")));
+        Ok(())
+    });
+    console.register_command("fail", &[], "always fails", |_, _| {
+        Err("it failed".to_string())
+    });
+    assert!(console.tab_command_table.contains(&"hi".to_string()));
+
+    // registering again replaces the aliases
+    console.register_command("greet", &["hello"], "say hello", |_, _| Ok(()));
+    assert!(!console.tab_command_table.contains(&"hi".to_string()));
+    assert!(console.tab_command_table.contains(&"hello".to_string()));
+    console.register_command("greet", &["hi"], "say hello", |console, args| {
+        console.write(&format!("hello {}", args[1..].join(" ")));
+        Ok(())
+    });
+
+    assert!(matches!(
+        console.run_registered("hi 'big world'".to_string()),
+        ConsoleEvent::None
+    ));
+    assert!(console.text.contains("hello big world"));
+
+    assert!(matches!(
+        console.run_registered("fail".to_string()),
+        ConsoleEvent::None
+    ));
+    assert!(console.text.contains("it failed"));
+
+    assert!(matches!(
+        console.run_registered("other".to_string()),
+        ConsoleEvent::Command(c) if c == "other"
+    ));
+}
+
+#[test]
+fn test_parse_commands() {
+    let mut console = crate::ConsoleBuilder::new().parse_commands(true).build();
+    assert_eq!(
+        console.run_registered("cp  'a b' \"c\\\"d\"".to_string()),
+        ConsoleEvent::CommandArgs {
+            raw: "cp  'a b' \"c\\\"d\"".to_string(),
+            args: vec!["cp".to_string(), "a b".to_string(), "c\"d".to_string()],
+        }
+    );
+    assert_eq!(
+        console.run_registered("echo 'oops".to_string()),
+        ConsoleEvent::None
+    );
+    assert!(console.text.ends_with("unbalanced ' quote\n>> "));
+
+    // the error is not redirected
+    let path = std::env::temp_dir().join(format!("egui_console_parse_{}.txt", std::process::id()));
+    console.set_alias("say", "echo '");
+    console.run_registered(format!("say hi > '{}'", path.display()));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(console.text.matches("unbalanced ' quote").count(), 2);
+}
+
+#[test]
+fn test_inline_help() {
+    let mut console = crate::ConsoleBuilder::new().inline_help(true).build();
+    console.register_command("greet", &["hi"], "say hello", |_, _| Ok(()));
+    console.prompt();
+    assert_eq!(console.inline_help_text(None), None);
+    console.text.push_str("hi bob");
+    assert_eq!(
+        console.inline_help_text(None),
+        Some(("greet (hi)  say hello".to_string(), None))
+    );
+
+    // the argument being typed is highlighted
+    console.register_command("cp", &[], "copy files", |_, _| Ok(()));
+    assert!(console.set_command_args("cp", &["<src>...", "<dest>"]));
+    assert!(!console.set_command_args("nope", &[]));
+    console.text.truncate(console.text.len() - "hi bob".len());
+    console.text.push_str("cp a");
+    let help = "cp <src>... <dest>  copy files".to_string();
+    assert_eq!(
+        console.inline_help_text(None),
+        Some((help.clone(), Some(3..11)))
+    );
+    console.text.push(' ');
+    assert_eq!(
+        console.inline_help_text(None),
+        Some((help.clone(), Some(12..18)))
+    );
+    // moving the cursor back to the first argument
+    let cursor = console.text.chars().count() - 2;
+    assert_eq!(
+        console.inline_help_text(Some(cursor)),
+        Some((help.clone(), Some(3..11)))
+    );
+    // on the command name nothing is highlighted
+    let cursor = console.text.chars().count() - 4;
+    assert_eq!(console.inline_help_text(Some(cursor)), Some((help, None)));
+
+    console.inline_help = false;
+    assert_eq!(console.inline_help_text(None), None);
+}
+
+#[test]
+fn test_unregister_command() {
+    let mut console = crate::ConsoleBuilder::new().build();
+    console.register_command("greet", &["hi"], "say hello", |_, _| Ok(()));
+    console.register_command("wave", &["hi"], "wave", |_, _| Ok(()));
+    console.register_command("once", &[], "runs once", |console, _| {
+        console.unregister_command("once");
+        Ok(())
+    });
+
+    assert!(!console.unregister_command("hi"));
+    assert!(console.unregister_command("greet"));
+    assert!(!console.tab_command_table.contains(&"greet".to_string()));
+    // wave still answers to hi
+    assert!(console.tab_command_table.contains(&"hi".to_string()));
+    // and a console alias keeps its completion
+    console.register_command("ll", &[], "list", |_, _| Ok(()));
+    console.set_alias("ll", "ls -l");
+    assert!(console.unregister_command("ll"));
+    assert!(console.tab_command_table.contains(&"ll".to_string()));
+    console.remove_alias("ll");
+
+    console.run_registered("once".to_string());
+    assert!(!console.command_help().contains("once"));
+    assert!(matches!(
+        console.run_registered("once".to_string()),
+        ConsoleEvent::Command(_)
+    ));
+
+    console.clear_commands();
+    assert!(console.command_help().is_empty());
+    assert!(console.tab_command_table.is_empty());
+}
@@ -1,23 +1,87 @@
-use std::{collections::VecDeque, str::Lines, sync::atomic::AtomicU16};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    str::Lines,
+    sync::{atomic::AtomicU16, Arc},
+};
 
 use egui::{
     text::CCursorRange, Align, Context, Event, EventFilter, Id, Key, Modifiers, TextEdit, Ui,
 };
 
-static SEARCH_PROMPT: &str = "(reverse-i-search) :";
-const SEARCH_PROMPT_SLOT_OFF: usize = 18;
+use crate::a11y::Announcements;
+use crate::chain::ChainedCommand;
+use crate::command::RegisteredCommand;
+use crate::drop::DropBehavior;
+use crate::find::{highlight_layout, FindBar};
+use crate::notify::Notification;
+use crate::paste::PASTE_LIMIT;
+use crate::prompt::PromptPlaceholder;
+use crate::redirect::Capture;
+use crate::scroll::{ScrollRequest, ScrollState};
+use crate::search::{CaseMode, LastSearch, MatchMode, RecallKeys};
+use crate::sink::SinkQueue;
+
 static INSTANCE_COUNT: AtomicU16 = AtomicU16::new(0);
 
 /// The event that was generated by the console
 ///
+/// [`ConsoleWindow::draw`] returns at most one event per frame. When more happen in one
+/// frame, for example enter and ctrl-c pressed together, the rest are queued and returned
+/// by the following frames in the order they happened.
 ///
+/// Lines typed or pasted after one was entered, before the host prompted again, are
+/// kept and entered one at a time after each [`ConsoleWindow::prompt`].
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum ConsoleEvent {
     /// A command was entered
     Command(String),
 
+    /// A command was entered, split into words
+    ///
+    /// Returned instead of [`ConsoleEvent::Command`] when
+    /// [`ConsoleBuilder::parse_commands`] is on. Quotes group words and are removed,
+    /// backslash escapes a character inside double quotes.
+    CommandArgs {
+        /// the line as entered, after alias expansion
+        raw: String,
+        /// the words of the line, the first is the command name
+        args: Vec<String>,
+    },
+
+    /// Ctrl-C was pressed, the line being typed has been abandoned
+    Interrupt,
+
+    /// Ctrl-D was pressed on an empty line
+    Eof,
+
+    /// A line was added to the history or the history was cleared
+    HistoryChanged,
+
+    /// The user cleared the console with ctrl-l
+    Cleared,
+
+    /// A file was dropped on the console, see [`ConsoleBuilder::drop_behavior`]
+    FileDropped(std::path::PathBuf),
+
     /// Nothing
     None,
 }
+/// What [`ConsoleWindow::draw_full`] returns
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ConsoleOutput {
+    /// the event that was generated by the console, as returned by [`ConsoleWindow::draw`]
+    pub event: ConsoleEvent,
+    /// the response of the text area, its rect is where the console was drawn
+    pub response: egui::Response,
+    /// does the console have keyboard focus
+    pub has_focus: bool,
+    /// is the view showing the last line, false once the user scrolled up
+    pub scrolled_to_bottom: bool,
+}
+
 /// Console Window  
 ///
 ///
@@ -30,15 +94,58 @@ pub struct ConsoleWindow {
     pub(crate) force_cursor_to_end: bool,
     history_size: usize,
     pub(crate) scrollback_size: usize,
-    command_history: VecDeque<String>,
+    // lines in text, kept up to date as output is added so writes don't have to count them
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) line_count: usize,
+    pub(crate) command_history: VecDeque<String>,
     #[cfg_attr(feature = "persistence", serde(skip))]
-    history_cursor: Option<usize>,
+    pub(crate) history_cursor: Option<usize>,
     pub(crate) prompt: String,
-    prompt_len: usize,
-    id: Id,
+    pub(crate) prompt_len: usize,
+    // the prompt from the builder, for when there is no saved one to go back to
+    #[cfg_attr(feature = "persistence", serde(default))]
+    builder_prompt: String,
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub(crate) prompt_template: Option<String>,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) prompt_placeholders: Vec<PromptPlaceholder>,
+    // set_prompt while the user was typing, used from the next line
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    next_prompt: Option<String>,
+    // a restored console gets a fresh id so it can't collide with one built since
+    #[cfg_attr(feature = "persistence", serde(skip, default = "next_id"))]
+    auto_id: Id,
+    // from ConsoleBuilder::id_source, kept so a restored console gets the same id
+    #[cfg_attr(feature = "persistence", serde(default))]
+    id_source: Option<Id>,
     save_prompt: Option<String>,
     #[cfg_attr(feature = "persistence", serde(skip))]
-    search_partial: Option<String>,
+    pub(crate) search_partial: Option<String>,
+    // the input line and the cursor in it when the search started, put back if it is aborted
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) search_saved_input: Option<(String, usize)>,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) last_search: Option<LastSearch>,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) recall_keys: RecallKeys,
+    // a char offset the cursor is moved to on the next frame
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) restore_cursor: Option<usize>,
+    // history indices matching the search, best first, and the one showing
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) search_matches: Vec<usize>,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) search_index: usize,
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub(crate) search_mode: MatchMode,
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub(crate) search_case: CaseMode,
+    // the regex query did not compile
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) search_invalid: bool,
+    // screen position of the top of the input line, the search popup sits above it
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) input_top: f32,
     // enable running stuff after serde reload
     #[cfg_attr(feature = "persistence", serde(skip))]
     init_done: bool,
@@ -54,6 +161,105 @@ pub struct ConsoleWindow {
     #[cfg_attr(feature = "persistence", serde(skip))]
     pub(crate) tab_offset: usize,
     pub(crate) tab_command_table: Vec<String>,
+    // the names added by clap_support::register
+    #[cfg(feature = "clap")]
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) clap_names: Vec<String>,
+
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub(crate) parse_commands: bool,
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub(crate) aliases: BTreeMap<String, String>,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) vars: HashMap<String, String>,
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub(crate) expand_vars: bool,
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub(crate) warn_unset_vars: bool,
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub(crate) inline_help: bool,
+
+    // pasting
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub(crate) keep_paste_controls: bool,
+    #[cfg_attr(
+        feature = "persistence",
+        serde(default = "crate::paste::paste_limit_default")
+    )]
+    pub(crate) paste_limit: usize,
+    // a long paste waiting for a yes
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) pending_paste: Option<String>,
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub(crate) drop_behavior: DropBehavior,
+
+    // screen readers
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub(crate) accessibility_off: bool,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) announcements: Announcements,
+
+    // command chaining
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub(crate) chain_commands: bool,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) chain: VecDeque<ChainedCommand>,
+    // how the last command went, None until it is known
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) chain_status: Option<bool>,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    // the error code of the last command, None if it succeeded
+    pub(crate) last_status: Option<i32>,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) prompt_deferred: bool,
+
+    // output redirection
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub(crate) redirect_disabled: bool,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) capture: Option<Capture>,
+
+    // find in output
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) find: Option<FindBar>,
+
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) notifications: VecDeque<Notification>,
+
+    // moving the view without moving the cursor
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) scroll_request: Option<ScrollRequest>,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) scroll_state: ScrollState,
+
+    // commands the console runs itself
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) commands: Vec<RegisteredCommand>,
+
+    // output written from other threads
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) sink_queue: Arc<SinkQueue>,
+    // the running command session
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) session: Option<u64>,
+
+    // events waiting to be returned by draw
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) events: VecDeque<ConsoleEvent>,
+    // typed after a line was entered in the same frame, replayed at the next prompt
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    type_ahead: String,
+    // the process started by spawn_process
+    #[cfg(all(feature = "process", not(target_arch = "wasm32")))]
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) process: Option<crate::process::ProcessHandle>,
+}
+
+fn next_id() -> Id {
+    Id::new(format!(
+        "console_text_{}",
+        INSTANCE_COUNT.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+    ))
 }
 
 impl ConsoleWindow {
@@ -65,14 +271,27 @@ impl ConsoleWindow {
             history_cursor: None,
             history_size: 100,
             scrollback_size: 1000,
+            line_count: 1,
             prompt: prompt.to_string(),
+            builder_prompt: prompt.to_string(),
             prompt_len: prompt.chars().count(),
-            id: Id::new(format!(
-                "console_text_{}",
-                INSTANCE_COUNT.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
-            )),
+            prompt_template: None,
+            prompt_placeholders: Vec::new(),
+            next_prompt: None,
+            auto_id: next_id(),
+            id_source: None,
             save_prompt: None,
             search_partial: None,
+            search_saved_input: None,
+            last_search: None,
+            recall_keys: RecallKeys::default(),
+            restore_cursor: None,
+            search_matches: Vec::new(),
+            search_index: 0,
+            search_mode: MatchMode::default(),
+            search_case: CaseMode::default(),
+            search_invalid: false,
+            input_top: 0.0,
             init_done: false,
 
             tab_string: String::new(),
@@ -81,6 +300,47 @@ impl ConsoleWindow {
             tab_quoted: false,
             tab_offset: usize::MAX,
             tab_command_table: Vec::new(),
+            #[cfg(feature = "clap")]
+            clap_names: Vec::new(),
+
+            parse_commands: false,
+            aliases: BTreeMap::new(),
+            vars: HashMap::new(),
+            expand_vars: false,
+            warn_unset_vars: false,
+            inline_help: false,
+            keep_paste_controls: false,
+            paste_limit: PASTE_LIMIT,
+            pending_paste: None,
+            drop_behavior: DropBehavior::default(),
+            accessibility_off: false,
+            announcements: Announcements::default(),
+
+            chain_commands: false,
+            chain: VecDeque::new(),
+            chain_status: None,
+            last_status: None,
+            prompt_deferred: false,
+
+            redirect_disabled: false,
+            capture: None,
+
+            find: None,
+
+            notifications: VecDeque::new(),
+
+            scroll_request: None,
+            scroll_state: ScrollState::default(),
+
+            commands: Vec::new(),
+
+            sink_queue: Arc::new(SinkQueue::default()),
+            session: None,
+
+            events: VecDeque::new(),
+            type_ahead: String::new(),
+            #[cfg(all(feature = "process", not(target_arch = "wasm32")))]
+            process: None,
         }
     }
     /// Draw the console window
@@ -90,43 +350,76 @@ impl ConsoleWindow {
     /// # Returns
     /// * `ConsoleEvent` - the event that was generated by the console
     ///
-
+    /// Only one event is returned per frame, see [`ConsoleEvent`].
+    ///
     pub fn draw(&mut self, ui: &mut Ui) -> ConsoleEvent {
+        self.draw_full(ui).event
+    }
+    /// Draw the console window, returning layout information too
+    /// # Arguments
+    /// * `ui` - the egui Ui context
+    ///
+    /// # Returns
+    /// * `ConsoleOutput` - the event along with the response, focus and scroll state
+    ///
+    /// Use this to anchor other ui, like a popup, to the console.
+    ///
+    pub fn draw_full(&mut self, ui: &mut Ui) -> ConsoleOutput {
         if !self.init_done {
             self.init_done = true;
+            // a restored console brings its text along
+            self.line_count = self.text.matches('\n').count() + 1;
             if let Some(prompt) = &self.save_prompt {
                 self.prompt.clone_from(prompt);
                 self.save_prompt = None;
             }
             self.draw_prompt();
+            self.replay_type_ahead();
         }
+        self.drain_sink(ui.ctx());
+        self.lock_input(ui);
         // do we need to handle keyboard events?
-        let msg = if ui.ctx().memory(|mem| mem.has_focus(self.id)) {
-            self.handle_kb(ui.ctx())
-        } else {
-            ConsoleEvent::None
+        if ui.ctx().memory(|mem| mem.has_focus(self.id())) {
+            self.handle_kb(ui.ctx());
+        }
+        // registered commands never make it to the host
+        let msg = match self.events.pop_front() {
+            Some(ConsoleEvent::Command(command)) => self.run_line(command),
+            Some(ConsoleEvent::Interrupt) => {
+                self.cancel_chain();
+                ConsoleEvent::Interrupt
+            }
+            Some(msg) => msg,
+            None => self.next_chained(),
         };
-        {
+        // the rest go out on the next frames
+        if !self.events.is_empty() || !self.chain.is_empty() || !self.type_ahead.is_empty() {
+            ui.ctx().request_repaint();
+        }
+        self.find_ui(ui);
+        let scroll = {
             let text_len = self.text.len();
-            self.ui(ui);
+            let scroll = self.ui(ui);
+            let rect = scroll.inner_rect;
+            self.notify_ui(ui, rect);
+            self.search_popup_ui(ui, rect);
+            self.inline_help_ui(ui, rect);
+            self.paste_prompt_ui(ui, rect);
+            self.busy_ui(ui, rect);
+            self.drop_ui(ui, rect);
+            self.accessibility_ui(ui);
 
             // did somebody type?
             if self.text.len() != text_len {
                 // yes - need to update partial search?
                 if self.search_partial.is_some() {
-                    self.search_partial = Some(self.get_search_text().to_string());
-                    self.prompt = SEARCH_PROMPT.to_string();
-                    self.prompt.insert_str(
-                        SEARCH_PROMPT_SLOT_OFF + 1,
-                        self.search_partial.as_ref().unwrap(),
-                    );
-                    self.history_cursor = None;
-                    self.history_back();
+                    self.search_typed(ui.ctx());
                 }
                 self.tab_string.clear();
                 self.tab_nth = 0;
             }
-        }
+            scroll
+        };
 
         // this is all so that we get the escape key (to exit search)
         let event_filter = EventFilter {
@@ -135,12 +428,22 @@ impl ConsoleWindow {
             vertical_arrows: true,
             tab: true, // we need the tab key for tab completion
         };
-        if ui.ctx().memory(|mem| mem.has_focus(self.id)) {
+        let has_focus = ui.ctx().memory(|mem| mem.has_focus(self.id()));
+        if has_focus {
             ui.ctx()
-                .memory_mut(|mem| mem.set_focus_lock_filter(self.id, event_filter));
+                .memory_mut(|mem| mem.set_focus_lock_filter(self.id(), event_filter));
         }
 
-        msg
+        // allow half a line of slack for rounding
+        let slack = ui.text_style_height(&egui::TextStyle::Monospace) / 2.0;
+        let scrolled_to_bottom =
+            scroll.state.offset.y + scroll.inner_rect.height() >= scroll.content_size.y - slack;
+        ConsoleOutput {
+            event: msg,
+            response: scroll.inner,
+            has_focus,
+            scrolled_to_bottom,
+        }
     }
     /// Write a line to the console
     /// # Arguments
@@ -148,10 +451,61 @@ impl ConsoleWindow {
     ///
     /// Note that you can call this without the user having typed anything.
     ///
+    /// While the output of a command is redirected with `>` or `>>` it goes to the file
+    /// instead.
+    ///
     pub fn write(&mut self, data: &str) {
+        if self.capture_text(&format!("{}\n", data)) {
+            return;
+        }
+        self.write_display(data);
+    }
+
+    // write to the screen even if output is redirected
+    pub(crate) fn write_display(&mut self, data: &str) {
+        self.write_line(data, false);
+    }
+
+    // the same for an error, screen readers read it out straight away
+    pub(crate) fn write_error_display(&mut self, data: &str) {
+        self.write_line(data, true);
+    }
+
+    fn write_line(&mut self, data: &str, error: bool) {
         self.text.push_str(&format!("\n{}", data));
+        self.line_count += 1 + data.matches('\n').count();
         self.truncate_scroll_back();
         self.force_cursor_to_end = true;
+        self.announce(data, error);
+    }
+
+    // write a line above the prompt, leaving whatever the user is typing alone
+    pub(crate) fn write_above_prompt(&mut self, ctx: &Context, data: &str) {
+        let last_off = self.last_line_offset();
+        if self.session.is_some() || !self.text[last_off..].starts_with(&self.prompt) {
+            self.write_display(data);
+            return;
+        }
+        // everything changes before the input line so the cursor keeps its distance from the end
+        let state = TextEdit::load_state(ctx, self.id());
+        let old_len = self.text.chars().count();
+        self.text.insert_str(last_off, &format!("{}\n", data));
+        self.line_count += 1 + data.matches('\n').count();
+        self.truncate_scroll_back();
+        self.announce(data, false);
+        if let Some(mut state) = state {
+            if let Some(range) = state.cursor.char_range() {
+                let new_len = self.text.chars().count();
+                let shift = |c: egui::text::CCursor| {
+                    egui::text::CCursor::new((new_len + c.index).saturating_sub(old_len))
+                };
+                state.cursor.set_char_range(Some(CCursorRange::two(
+                    shift(range.secondary),
+                    shift(range.primary),
+                )));
+                state.store(ctx, self.id());
+            }
+        }
     }
 
     /// Loads the history from an iterator of strings
@@ -177,16 +531,120 @@ impl ConsoleWindow {
     pub fn clear_history(&mut self) {
         self.command_history.clear();
         self.history_cursor = None;
+        self.events.push_back(ConsoleEvent::HistoryChanged);
     }
 
     /// Clear the console
     pub fn clear(&mut self) {
         self.text.clear();
+        self.line_count = 1;
         self.force_cursor_to_end = false;
     }
     /// Prompt the user for input
+    ///
+    /// While the pieces of a chained line are still running this is held back until the
+    /// last one, see [`ConsoleBuilder::chain_commands`].
+    ///
     pub fn prompt(&mut self) {
+        self.end_redirect();
+        if !self.chain.is_empty() {
+            self.prompt_deferred = true;
+            return;
+        }
         self.draw_prompt();
+        self.replay_type_ahead();
+    }
+    /// Run a command as if the user typed it and pressed enter
+    /// # Arguments
+    /// * `command` - the command line
+    ///
+    /// The line is echoed after the prompt, added to the history and returned by the next
+    /// [`ConsoleWindow::draw`], after alias and variable expansion like a typed line. What
+    /// the user had typed is put back after the next prompt. While a command is still
+    /// running the line waits for the next [`ConsoleWindow::prompt`].
+    ///
+    pub fn run_command(&mut self, command: &str) {
+        let line = format!("{}\n", command.replace('\n', " "));
+        let showing = self.init_done
+            && self.session.is_none()
+            && self.chain.is_empty()
+            && self.text[self.last_line_offset()..].starts_with(&self.prompt);
+        if !showing {
+            self.type_ahead.push_str(&line);
+            return;
+        }
+        if self.search_partial.is_some() {
+            self.exit_search_mode();
+        }
+        let typed = self.get_last_line().to_string();
+        self.text.truncate(self.text.len() - typed.len());
+        self.type_ahead.insert_str(0, &format!("{}{}", line, typed));
+        self.replay_type_ahead();
+    }
+    /// Run a command without echoing it or adding it to the history
+    /// # Arguments
+    /// * `command` - the command line
+    ///
+    /// The line is returned by the next [`ConsoleWindow::draw`] as a
+    /// [`ConsoleEvent::Command`], registered commands and aliases still apply.
+    ///
+    pub fn inject_silently(&mut self, command: &str) {
+        self.events
+            .push_back(ConsoleEvent::Command(command.to_string()));
+    }
+    /// Change the prompt
+    /// # Arguments
+    /// * `prompt` - the new prompt, this also turns off a prompt template
+    ///
+    /// If the prompt is showing and nothing has been typed after it, it is replaced
+    /// straight away. Otherwise the new prompt is used from the next line.
+    ///
+    pub fn set_prompt(&mut self, prompt: &str) {
+        self.prompt_template = None;
+        // reverse search puts the prompt back when it exits, until then the cursor is
+        // kept to the line drawn with the old one
+        if self.search_partial.is_some() {
+            self.save_prompt = Some(prompt.to_string());
+            return;
+        }
+        let last_off = self.last_line_offset();
+        if self.init_done && self.text[last_off..] == self.prompt {
+            self.text.truncate(last_off);
+            self.text.push_str(prompt);
+            self.force_cursor_to_end = true;
+        } else if self.init_done {
+            // the line being typed keeps the prompt it was drawn with
+            self.next_prompt = Some(prompt.to_string());
+            return;
+        }
+        self.prompt = prompt.to_string();
+        self.prompt_len = prompt.chars().count();
+    }
+    /// Get the egui id of the console's text area
+    /// # Returns
+    /// * `Id` - from [`ConsoleBuilder::id_source`] if it was set, otherwise unique to this console
+    ///
+    pub fn id(&self) -> Id {
+        self.id_source.unwrap_or(self.auto_id)
+    }
+    /// Give the console keyboard focus
+    /// # Arguments
+    /// * `ctx` - the egui context
+    ///
+    /// Call this when the console is shown so typing goes straight to it.
+    ///
+    pub fn request_focus(&self, ctx: &Context) {
+        ctx.memory_mut(|mem| mem.request_focus(self.id()));
+    }
+    /// Does the console have keyboard focus
+    /// # Arguments
+    /// * `ctx` - the egui context
+    ///
+    /// # Returns
+    /// * `bool` - true if keys go to the console, handy for turning off app hotkeys
+    ///
+    pub fn has_focus(&self, ctx: &Context) -> bool {
+        ctx.memory(|mem| mem.has_focus(self.id()))
     }
     /// get mut ref to tab completion table for commands
     pub fn command_table_mut(&mut self) -> &mut Vec<String> {
@@ -202,17 +660,33 @@ impl ConsoleWindow {
         }
         egui::text::CCursorRange::one(egui::text::CCursor::new(loc))
     }
-    fn ui(&mut self, ui: &mut egui::Ui) {
-        egui::ScrollArea::both().show(ui, |ui| {
+    fn ui(&mut self, ui: &mut egui::Ui) -> egui::scroll_area::ScrollAreaOutput<egui::Response> {
+        let highlights = self.find_highlights();
+        let id = self.id();
+        let mut layouter = |ui: &Ui, text: &dyn egui::TextBuffer, wrap_width: f32| {
+            let job = highlight_layout(ui, text.as_str(), &highlights, wrap_width);
+            ui.fonts(|f| f.layout_job(job))
+        };
+        let mut area = egui::ScrollArea::both().id_salt(id.with("scroll"));
+        if let Some(offset) = self.take_scroll_offset(ui) {
+            area = area.vertical_scroll_offset(offset);
+        }
+        let output = area.show(ui, |ui| {
             ui.add_sized(ui.available_size(), |ui: &mut Ui| {
-                let widget = egui::TextEdit::multiline(&mut self.text)
+                let mut widget = egui::TextEdit::multiline(&mut self.text)
                     .font(egui::TextStyle::Monospace)
                     .frame(false)
                     .code_editor()
                     .lock_focus(true)
                     .desired_width(f32::INFINITY)
-                    .id(self.id);
+                    .id(id);
+                // only pay for the custom layout while there is something to highlight
+                if !highlights.is_empty() {
+                    widget = widget.layouter(&mut layouter);
+                }
                 let output = widget.show(ui);
+                let row_height = output.galley.rows.last().map_or(0.0, |row| row.height());
+                self.input_top = output.galley_pos.y + output.galley.rect.bottom() - row_height;
                 let mut new_cursor = None;
 
                 // fix up cursor position
@@ -222,26 +696,23 @@ impl ConsoleWindow {
 
                 match self.search_partial {
                     Some(_) => {
+                        let (start, end) = self.search_bounds();
                         if let Some(cursor) = output.state.cursor.char_range() {
-                            let last_off = self.last_line_offset();
-                            if cursor.primary.index < (last_off + SEARCH_PROMPT_SLOT_OFF + 1) {
-                                new_cursor =
-                                    Some(self.cursor_at(last_off + SEARCH_PROMPT_SLOT_OFF + 1));
-                            } else {
-                                let search_text = self.get_search_text();
-                                if cursor.primary.index
-                                    > (last_off + SEARCH_PROMPT.len() + search_text.len())
-                                {
-                                    new_cursor = Some(self.cursor_at(
-                                        last_off + SEARCH_PROMPT_SLOT_OFF + search_text.len() + 1,
-                                    ));
-                                }
+                            if cursor.primary.index < start {
+                                new_cursor = Some(self.cursor_at(start));
+                            } else if cursor.primary.index > end {
+                                new_cursor = Some(self.cursor_at(end));
                             }
                         }
+                        // the search line was redrawn
+                        if self.force_cursor_to_end {
+                            new_cursor = Some(self.cursor_at(end));
+                            self.force_cursor_to_end = false;
+                        }
                     }
                     None => {
                         if let Some(cursor) = output.state.cursor.char_range() {
-                            let last_off = self.last_line_offset();
+                            let last_off = self.last_line_char_offset();
                             if cursor.primary.index < last_off + self.prompt_len - 1 {
                                 new_cursor = Some(self.cursor_at_end());
                             }
@@ -252,6 +723,9 @@ impl ConsoleWindow {
                             new_cursor = Some(self.cursor_at_end());
                             self.force_cursor_to_end = false;
                         }
+                        if let Some(at) = self.restore_cursor.take() {
+                            new_cursor = Some(self.cursor_at(at));
+                        }
                     }
                 };
 
@@ -264,9 +738,16 @@ impl ConsoleWindow {
                     }
                     ui.scroll_to_cursor(Some(Align::BOTTOM));
                 }
+                self.scroll_to_find_match(ui, &output);
                 output.response
-            });
+            })
         });
+        self.store_scroll(
+            output.state.offset.y,
+            output.inner_rect.height(),
+            output.content_size.y,
+        );
+        output
     }
 
     pub(crate) fn get_last_line(&self) -> &str {
@@ -277,32 +758,26 @@ impl ConsoleWindow {
             .strip_prefix(&self.prompt)
             .unwrap_or("")
     }
-    fn truncate_scroll_back(&mut self) {
-        let line_count = self.text.lines().count();
-        if line_count < self.scrollback_size {
+    // drop lines from the front once there are a tenth more than the scrollback size, so a
+    // burst of output only pays for it every so often
+    pub(crate) fn truncate_scroll_back(&mut self) {
+        if self.line_count <= self.scrollback_size + self.scrollback_size / 10 {
             return;
         }
-        let mut scrollback = String::with_capacity(self.text.len());
-
-        for (i, line) in self.text.lines().enumerate() {
-            if i > line_count - self.scrollback_size {
-                scrollback.push_str(line);
-                scrollback.push('\n');
+        // cut at the newline before the first line kept, the last line is always kept
+        let keep = self.scrollback_size.max(1);
+        match self.text.rmatch_indices('\n').nth(keep - 1) {
+            Some((cut, _)) => {
+                self.text.drain(..=cut);
+                self.line_count = keep;
             }
+            // some text was added without being counted
+            None => self.line_count = self.text.matches('\n').count() + 1,
         }
-        self.text = scrollback;
     }
-    fn get_search_text(&self) -> &str {
-        let last = self.text.lines().last().unwrap_or("");
-        let mut iter = last.char_indices();
-        let (start, _) = iter.nth(SEARCH_PROMPT_SLOT_OFF + 1).unwrap_or((0, ' '));
-        for (end, ch) in iter {
-            // TODO - this will fail if the search text contains ':'
-            if ch == ':' {
-                return &last[start..end];
-            }
-        }
-        ""
+    pub(crate) fn get_search_text(&self) -> String {
+        let (start, end) = self.search_bounds();
+        self.text.chars().skip(start).take(end - start).collect()
     }
     fn consume_key(ctx: &Context, modifiers: Modifiers, logical_key: Key) {
         ctx.input_mut(|inp| inp.consume_key(modifiers, logical_key));
@@ -313,14 +788,26 @@ impl ConsoleWindow {
         key: &Key,
         modifiers: Modifiers,
         cursor: usize,
-    ) -> (bool, Option<String>) {
-        // return value is (consume_key, command)
+    ) -> (bool, Option<ConsoleEvent>) {
+        // return value is (consume_key, event)
+
+        // paging moves the view, not the cursor
+        if self.scroll_key(modifiers, *key) {
+            return (true, None);
+        }
+        // alt-n and alt-p go back into the search that was just accepted
+        if let Some(step) = self.recall_step(modifiers, *key) {
+            if self.recall_search(step, cursor) {
+                return (true, None);
+            }
+        }
 
         let return_value = match (modifiers, key) {
             (Modifiers::NONE, Key::ArrowDown) => {
-                // down arrow only means something if we are in search mode
+                // in search mode up and down move through the matches
                 if self.search_partial.is_some() {
-                    self.exit_search_mode()
+                    self.next_search_match();
+                    return (true, None);
                 };
                 if let Some(mut hc) = self.history_cursor {
                     let last = self.get_last_line();
@@ -342,64 +829,67 @@ impl ConsoleWindow {
                     return (true, None);
                 }
                 if self.search_partial.is_some() {
-                    self.exit_search_mode()
+                    self.previous_search_match();
+                    return (true, None);
                 };
 
                 self.history_back();
                 (true, None)
             }
-            (Modifiers::NONE, Key::Enter) => {
+            (Modifiers::NONE, Key::Enter) => (true, Some(self.enter_line())),
+            // ctrl-c on the mac, elsewhere it arrives as a copy event
+            (modifiers, Key::C) if modifiers.ctrl && !modifiers.alt && !modifiers.shift => {
+                (true, Some(self.interrupt()))
+            }
+            // ctrl-d on an empty line
+            (modifiers, Key::D)
+                if modifiers.ctrl
+                    && !modifiers.alt
+                    && !modifiers.shift
+                    && self.search_partial.is_none()
+                    && self.get_last_line().is_empty() =>
+            {
+                (true, Some(ConsoleEvent::Eof))
+            }
+            // ctrl-l clears the screen but keeps the line being typed
+            (modifiers, Key::L)
+                if modifiers.ctrl
+                    && !modifiers.alt
+                    && !modifiers.shift
+                    && self.search_partial.is_none() =>
+            {
                 let last = self.get_last_line().to_string();
-                if self.search_partial.is_some() {
-                    self.exit_search_mode()
-                };
-                if self.command_history.len() >= self.history_size {
-                    self.command_history.pop_front();
-                }
-                self.command_history.push_back(last.clone());
-
+                self.clear();
+                self.draw_prompt();
+                self.text.push_str(&last);
                 self.force_cursor_to_end = true;
-                self.history_cursor = None;
-                self.truncate_scroll_back();
-                (true, Some(last))
+                (true, Some(ConsoleEvent::Cleared))
             }
 
             // in search mode the cursor is constrained to the inside of the
             // search prompt. In mormal mode the cursor is constrained to the
             // right of the prompt
-            (Modifiers::NONE, Key::Delete) => {
-                if let Some(search_partial) = &self.search_partial {
-                    let last_off = self.last_line_offset();
-                    if cursor > (last_off + SEARCH_PROMPT.len() - 2 + search_partial.len()) {
-                        return (true, None);
-                    }
-                }
-                (false, None)
-            }
-            (Modifiers::NONE, Key::ArrowRight) => {
+            (Modifiers::NONE, Key::Delete) | (Modifiers::NONE, Key::ArrowRight) => {
                 // nothing to do in normal mode. In search mode we need to
-                // constrain the cursor to the search prompt
-                if let Some(search_partial) = &self.search_partial {
-                    let last_off = self.last_line_offset();
-
-                    if cursor > (last_off + SEARCH_PROMPT.len() - 2 + search_partial.len()) {
-                        return (true, None);
-                    }
+                // constrain the cursor to the search query
+                if self.search_partial.is_some() && cursor >= self.search_bounds().1 {
+                    return (true, None);
                 }
                 (false, None)
             }
             (Modifiers::NONE, Key::ArrowLeft) | (Modifiers::NONE, Key::Backspace) => {
                 // in either mode dont allow motion (or deleting) into prompt
 
-                let last_off = self.last_line_offset();
+                let last_off = self.last_line_char_offset();
                 match self.search_partial {
                     Some(_) => {
-                        if cursor < (last_off + SEARCH_PROMPT_SLOT_OFF + 2) {
+                        if cursor <= self.search_bounds().0 {
                             return (true, None);
                         }
                     }
                     None => {
-                        if cursor < (last_off + self.prompt.len() + 1) {
+                        // the prompt can change from line to line, prompt_len is the last one drawn
+                        if cursor < (last_off + self.prompt_len + 1) {
                             return (true, None);
                         }
                     }
@@ -407,13 +897,23 @@ impl ConsoleWindow {
 
                 (false, None)
             }
+            // escape or ctrl-g abort the search and put back what was typed before it
             (Modifiers::NONE, Key::Escape) => {
                 if self.search_partial.is_some() {
-                    self.exit_search_mode()
+                    self.abort_search()
                 };
                 self.history_cursor = None;
                 (true, None)
             }
+            (modifiers, Key::G)
+                if modifiers.ctrl
+                    && !modifiers.alt
+                    && !modifiers.shift
+                    && self.search_partial.is_some() =>
+            {
+                self.abort_search();
+                (true, None)
+            }
 
             // ctrl-r reverse search history
             (
@@ -427,14 +927,51 @@ impl ConsoleWindow {
                 Key::R,
             ) => {
                 if self.search_partial.is_none() {
-                    self.search_partial = Some(String::new());
-                    self.enter_search_mode();
+                    self.enter_search_mode(cursor);
                 } else {
-                    self.history_back();
+                    self.next_search_match();
                 }
                 (true, None)
             }
+            // alt-r switches how the search matches
+            (Modifiers::ALT, Key::R) if self.search_partial.is_some() => {
+                self.next_search_mode();
+                (true, None)
+            }
+            // ctrl-shift-r finds in output and lists the matching lines
+            (
+                Modifiers {
+                    alt: false,
+                    ctrl: true,
+                    shift: true,
+                    mac_cmd: false,
+                    command: true,
+                },
+                Key::R,
+            ) if self.search_partial.is_none() => {
+                self.open_find_list();
+                (true, None)
+            }
+            // ctrl-f find in output
+            (
+                Modifiers {
+                    alt: false,
+                    ctrl: true,
+                    shift: false,
+                    mac_cmd: false,
+                    command: true,
+                },
+                Key::F,
+            ) => {
+                self.open_find();
+                (true, None)
+            }
             (Modifiers::NONE, Key::Tab) => {
+                // in search mode tab takes the match to edit it
+                if self.search_partial.is_some() {
+                    self.accept_search();
+                    return (true, None);
+                }
                 // off to tab completion land
                 self.tab_complete();
                 (true, None)
@@ -453,26 +990,9 @@ impl ConsoleWindow {
         };
 
         let mut hist_line = String::new();
-        for i in (0..hc).rev() {
-            match &self.search_partial {
-                Some(search) => {
-                    if search.is_empty() {
-                        self.history_cursor = None;
-                        break;
-                    }
-
-                    if self.command_history[i].contains(search) {
-                        hist_line = self.command_history[i].clone();
-                        self.history_cursor = Some(i);
-                        break;
-                    }
-                }
-                None => {
-                    hist_line = self.command_history[i].clone();
-                    self.history_cursor = Some(i);
-                    break;
-                }
-            }
+        if let Some(i) = hc.checked_sub(1) {
+            hist_line = self.command_history[i].clone();
+            self.history_cursor = Some(i);
         }
 
         if !hist_line.is_empty() {
@@ -482,21 +1002,46 @@ impl ConsoleWindow {
         }
     }
 
-    fn last_line_offset(&self) -> usize {
+    pub(crate) fn last_line_offset(&self) -> usize {
         // offset in buffer of start of last line
         self.text.rfind('\n').map_or(0, |off| off + 1)
     }
-    fn enter_search_mode(&mut self) {
+    // the same in chars, which is what the cursor counts
+    pub(crate) fn last_line_char_offset(&self) -> usize {
+        self.text[..self.last_line_offset()].chars().count()
+    }
+    // the cursor is the char offset in the text, it is put back there if the search is aborted
+    // does nothing if a search is already going, it would save the search prompt
+    pub(crate) fn enter_search_mode(&mut self, cursor: usize) {
+        if self.search_partial.is_some() {
+            return;
+        }
+        let last_off = self.last_line_offset();
+        let input = self.text[last_off..]
+            .strip_prefix(self.prompt.as_str())
+            .unwrap_or("")
+            .to_string();
+        let input_start = self.last_line_char_offset() + self.prompt_len;
+        let at = cursor
+            .saturating_sub(input_start)
+            .min(input.chars().count());
+        self.search_saved_input = Some((input, at));
         self.save_prompt = Some(self.prompt.clone());
-        self.prompt = SEARCH_PROMPT.to_string();
         self.search_partial = Some(String::new());
+        self.search_matches.clear();
+        self.prompt = self.search_prompt();
         let last_off = self.last_line_offset();
         self.text.truncate(last_off);
         self.draw_prompt();
         self.force_cursor_to_end = true;
     }
-    fn exit_search_mode(&mut self) {
-        self.prompt = self.save_prompt.take().unwrap();
+    pub(crate) fn exit_search_mode(&mut self) {
+        self.prompt = self
+            .save_prompt
+            .take()
+            .unwrap_or_else(|| self.builder_prompt.clone());
+        self.prompt_len = self.prompt.chars().count();
+        self.search_saved_input = None;
 
         let last_off = self.last_line_offset();
         self.text.truncate(last_off);
@@ -504,73 +1049,257 @@ impl ConsoleWindow {
         self.search_partial = None;
         self.force_cursor_to_end = true;
     }
-    fn draw_prompt(&mut self) {
+    pub(crate) fn draw_prompt(&mut self) {
+        if let Some(prompt) = self.next_prompt.take() {
+            self.prompt_len = prompt.chars().count();
+            self.prompt = prompt;
+        }
+        if self.search_partial.is_none() {
+            self.render_prompt();
+        }
         if !self.text.is_empty() && !self.text.ends_with('\n') {
             self.text.push('\n');
+            self.line_count += 1;
         }
         self.text.push_str(&self.prompt);
     }
 
-    fn handle_kb(&mut self, ctx: &egui::Context) -> ConsoleEvent {
+    // the line after the prompt goes into the history and out to the host
+    fn enter_line(&mut self) -> ConsoleEvent {
+        if self.search_partial.is_some() {
+            self.accept_search();
+        };
+        self.last_search = None;
+        let last = self.get_last_line().to_string();
+        if self.command_history.len() >= self.history_size {
+            self.command_history.pop_front();
+        }
+        self.command_history.push_back(last.clone());
+
+        self.force_cursor_to_end = true;
+        self.history_cursor = None;
+        self.truncate_scroll_back();
+        ConsoleEvent::Command(last)
+    }
+
+    // type text the console took from the input, every newline enters a line
+    pub(crate) fn type_text(&mut self, text: &str, cursor: &mut usize, entered: &mut bool) {
+        let text = text.replace("\r\n", "\n");
+        if *entered {
+            self.type_ahead.push_str(&text);
+            return;
+        }
+        let (line, rest) = match text.split_once('\n') {
+            Some((line, rest)) => (line, Some(rest)),
+            None => (text.as_str(), None),
+        };
+        // never type into the prompt
+        *cursor = (*cursor).max(self.last_line_char_offset() + self.prompt_len);
+        let at = self
+            .text
+            .char_indices()
+            .nth(*cursor)
+            .map_or(self.text.len(), |(i, _)| i);
+        self.text.insert_str(at, line);
+        *cursor += line.chars().count();
+        self.force_cursor_to_end = true;
+        if let Some(rest) = rest {
+            let msg = self.enter_line();
+            self.events.push_back(msg);
+            self.events.push_back(ConsoleEvent::HistoryChanged);
+            *entered = true;
+            self.type_ahead.push_str(rest);
+        }
+    }
+
+    // the host prompted again, enter the next line typed ahead
+    fn replay_type_ahead(&mut self) {
+        if self.type_ahead.is_empty() {
+            return;
+        }
+        let typed = std::mem::take(&mut self.type_ahead);
+        let mut cursor = self.text.chars().count();
+        let mut entered = false;
+        self.type_text(&typed, &mut cursor, &mut entered);
+    }
+
+    fn handle_kb(&mut self, ctx: &egui::Context) {
         // process all the key events in the queue
         // if they are meaningful to the console then use them and consume them
         // otherwise pass along to the textedit widget
 
         // current cursor position
 
-        let cursor = if let Some(state) = egui::TextEdit::load_state(ctx, self.id) {
-            state.cursor.char_range().unwrap().primary.index
-        } else {
-            0
-        };
+        // no cursor yet if focus was given without a click
+        let mut cursor = egui::TextEdit::load_state(ctx, self.id())
+            .and_then(|state| state.cursor.char_range())
+            .map_or(0, |range| range.primary.index);
+        let has_selection = self.has_selection(ctx);
+
+        // a long paste is waiting for a yes or no, nothing else goes to the line
+        if self.confirm_paste(ctx, &mut cursor) {
+            return;
+        }
+        self.filter_pastes(ctx);
 
         // a list of keys to consume
 
         let mut kill_list = vec![];
-        let mut command = None;
-        ctx.input(|input| {
+        let mut entered = false;
+        let take_typing = ctx.input(|input| {
+            // with a line entered this frame the typing around it has to land in order, so
+            // the console types it rather than the text edit
+            let take_typing = self.search_partial.is_none()
+                && input.events.iter().any(|event| match event {
+                    Event::Key {
+                        key: Key::Enter,
+                        pressed: true,
+                        modifiers,
+                        ..
+                    } => modifiers.is_none(),
+                    Event::Paste(text) => text.contains('\n'),
+                    _ => false,
+                });
             for event in &input.events {
-                if let Event::Key {
-                    key,
-                    physical_key: _,
-                    pressed,
-                    modifiers,
-                    repeat: _,
-                } = event
-                {
-                    if *pressed {
+                match event {
+                    Event::Key {
+                        key,
+                        physical_key: _,
+                        pressed: true,
+                        modifiers,
+                        repeat: _,
+                    } => {
+                        // once the user pressed enter the line belongs to the host, only
+                        // interrupts still count
+                        if entered {
+                            if *key == Key::C && modifiers.ctrl {
+                                kill_list.push((*modifiers, *key));
+                                self.type_ahead.clear();
+                                self.events.push_back(ConsoleEvent::Interrupt);
+                            } else if *key == Key::Enter && modifiers.is_none() {
+                                kill_list.push((*modifiers, *key));
+                                self.type_ahead.push('\n');
+                            }
+                            continue;
+                        }
                         let (kill, msg) = self.handle_key(key, *modifiers, cursor);
                         if kill {
                             kill_list.push((*modifiers, *key));
                         }
-                        command = msg;
-                        // if the user pressed enter we are done
-                        if command.is_some() {
-                            break;
+                        if let Some(msg) = msg {
+                            let command = matches!(msg, ConsoleEvent::Command(_));
+                            self.events.push_back(msg);
+                            // the line went into the history
+                            if command {
+                                entered = true;
+                                self.events.push_back(ConsoleEvent::HistoryChanged);
+                            }
                         }
                     }
+                    Event::Text(text) | Event::Paste(text) if take_typing => {
+                        self.type_text(text, &mut cursor, &mut entered);
+                    }
+                    Event::Copy if input.modifiers.ctrl && !has_selection => {
+                        if entered {
+                            self.type_ahead.clear();
+                            self.events.push_back(ConsoleEvent::Interrupt);
+                        } else {
+                            let msg = self.interrupt();
+                            self.events.push_back(msg);
+                        }
+                    }
+                    _ => {}
                 }
             }
+            take_typing
         });
 
         // consume the keys we used
         for (modifiers, key) in kill_list {
             Self::consume_key(ctx, modifiers, key);
         }
+        if take_typing {
+            ctx.input_mut(|inp| {
+                inp.events
+                    .retain(|event| !matches!(event, Event::Text(_) | Event::Paste(_)))
+            });
+        }
+    }
 
-        if let Some(command) = command {
-            return ConsoleEvent::Command(command);
+    // abandon the line being typed and start a new one
+    fn interrupt(&mut self) -> ConsoleEvent {
+        if self.search_partial.is_some() {
+            self.exit_search_mode();
         }
-        ConsoleEvent::None
+        self.text.push_str("^C");
+        self.history_cursor = None;
+        self.draw_prompt();
+        self.force_cursor_to_end = true;
+        ConsoleEvent::Interrupt
+    }
+
+    // a dim line of text just above the input line that never takes the focus
+    pub(crate) fn input_hint_ui(
+        &self,
+        ui: &Ui,
+        rect: egui::Rect,
+        salt: &str,
+        text: &str,
+        highlight: Option<std::ops::Range<usize>>,
+    ) {
+        let bottom = self.input_top.clamp(rect.top(), rect.bottom());
+        let font = egui::TextStyle::Monospace.resolve(ui.style());
+        let weak = egui::TextFormat::simple(font.clone(), ui.visuals().weak_text_color());
+        let strong = egui::TextFormat::simple(font, ui.visuals().strong_text_color());
+        let mut job = egui::text::LayoutJob::default();
+        match highlight {
+            Some(range) => {
+                job.append(&text[..range.start], 0.0, weak.clone());
+                job.append(&text[range.clone()], 0.0, strong);
+                job.append(&text[range.end..], 0.0, weak);
+            }
+            None => job.append(text, 0.0, weak),
+        }
+        egui::Area::new(self.id().with(salt))
+            .order(egui::Order::Foreground)
+            .interactable(false)
+            .pivot(egui::Align2::LEFT_BOTTOM)
+            .fixed_pos(egui::pos2(rect.left(), bottom))
+            .show(ui.ctx(), |ui| {
+                ui.label(job);
+            });
+    }
+
+    // is some of the text selected, then ctrl-c copies rather than interrupts
+    pub(crate) fn has_selection(&self, ctx: &Context) -> bool {
+        egui::TextEdit::load_state(ctx, self.id())
+            .and_then(|state| state.cursor.char_range())
+            .is_some_and(|range| !range.is_empty())
     }
 }
 /// A builder for the console window
 ///
 pub struct ConsoleBuilder {
     prompt: String,
+    prompt_template: Option<String>,
     history_size: usize,
     scrollback_size: usize,
     tab_quote_character: char,
+    sink_limit: usize,
+    parse_commands: bool,
+    search_mode: MatchMode,
+    search_case: CaseMode,
+    recall_keys: RecallKeys,
+    expand_vars: bool,
+    warn_unset_vars: bool,
+    inline_help: bool,
+    paste_filter: bool,
+    paste_limit: usize,
+    drop_behavior: DropBehavior,
+    accessibility: bool,
+    chain_commands: bool,
+    redirect_output: bool,
+    id_source: Option<Id>,
 }
 
 impl Default for ConsoleBuilder {
@@ -587,9 +1316,25 @@ impl ConsoleBuilder {
     pub fn new() -> Self {
         Self {
             prompt: ">> ".to_string(),
+            prompt_template: None,
             history_size: 100,
             scrollback_size: 1000,
             tab_quote_character: '\'',
+            parse_commands: false,
+            search_mode: MatchMode::default(),
+            search_case: CaseMode::default(),
+            recall_keys: RecallKeys::default(),
+            expand_vars: false,
+            warn_unset_vars: false,
+            inline_help: false,
+            paste_filter: true,
+            paste_limit: PASTE_LIMIT,
+            drop_behavior: DropBehavior::default(),
+            accessibility: true,
+            chain_commands: false,
+            redirect_output: true,
+            id_source: None,
+            sink_limit: 0,
         }
     }
     /// Set the prompt for the console
@@ -616,7 +1361,9 @@ impl ConsoleBuilder {
     }
     /// Set the scrollback size for the console
     /// # Arguments
-    /// * `size` - the size of the scrollback
+    /// * `size` - the size of the scrollback in lines
+    ///
+    /// Old lines are dropped in batches, once there are a tenth more than this.
     ///
     /// # Returns
     /// * `ConsoleBuilder` - the console builder
@@ -638,6 +1385,240 @@ impl ConsoleBuilder {
         self.tab_quote_character = quote;
         self
     }
+    /// Bound the queue used by [`ConsoleWindow::sink`]
+    /// # Arguments
+    /// * `limit` - the most messages waiting to be drawn, 0 for no limit
+    ///
+    /// When the queue is full the oldest output is dropped and counted.
+    ///
+    /// # Returns
+    /// * `ConsoleBuilder` - the console builder
+    ///
+    pub fn sink_limit(mut self, limit: usize) -> Self {
+        self.sink_limit = limit;
+        self
+    }
+    /// Choose how reverse-i-search matches the history
+    /// # Arguments
+    /// * `mode` - the [`MatchMode`], fuzzy by default
+    ///
+    /// Alt-r while searching switches to the next mode.
+    ///
+    /// # Returns
+    /// * `ConsoleBuilder` - the console builder
+    ///
+    pub fn history_match_mode(mut self, mode: MatchMode) -> Self {
+        self.search_mode = mode;
+        self
+    }
+    /// Choose how reverse-i-search treats case
+    /// # Arguments
+    /// * `case` - the [`CaseMode`], smart case by default: case is ignored unless the query
+    ///   has an upper case letter
+    ///
+    /// # Returns
+    /// * `ConsoleBuilder` - the console builder
+    ///
+    pub fn history_case_mode(mut self, case: CaseMode) -> Self {
+        self.search_case = case;
+        self
+    }
+    /// Choose the keys that open the last accepted search again
+    /// # Arguments
+    /// * `next` - steps to the match after the accepted one, alt-n by default
+    /// * `previous` - steps to the match before it, alt-p by default
+    ///
+    /// They work while the match taken with tab is still on the input line unchanged.
+    ///
+    /// # Returns
+    /// * `ConsoleBuilder` - the console builder
+    ///
+    pub fn history_recall_keys(
+        mut self,
+        next: egui::KeyboardShortcut,
+        previous: egui::KeyboardShortcut,
+    ) -> Self {
+        self.recall_keys = RecallKeys { next, previous };
+        self
+    }
+    /// Split entered commands into words
+    /// # Arguments
+    /// * `parse` - true to get [`ConsoleEvent::CommandArgs`] instead of [`ConsoleEvent::Command`]
+    ///
+    /// A line with unbalanced quotes is not returned, an error is written and the user
+    /// is prompted again.
+    ///
+    /// # Returns
+    /// * `ConsoleBuilder` - the console builder
+    ///
+    pub fn parse_commands(mut self, parse: bool) -> Self {
+        self.parse_commands = parse;
+        self
+    }
+    /// Replace `$name` variables in entered lines, see [`ConsoleWindow::set_var`]
+    /// # Arguments
+    /// * `expand` - true to expand variables, it is off by default so `$` reaches the host
+    ///   untouched
+    ///
+    /// # Returns
+    /// * `ConsoleBuilder` - the console builder
+    ///
+    pub fn expand_vars(mut self, expand: bool) -> Self {
+        self.expand_vars = expand;
+        self
+    }
+    /// Write a warning when an entered line uses a variable that is not set
+    /// # Arguments
+    /// * `warn` - true to warn, unset variables are replaced with nothing either way
+    ///
+    /// # Returns
+    /// * `ConsoleBuilder` - the console builder
+    ///
+    pub fn warn_unset_vars(mut self, warn: bool) -> Self {
+        self.warn_unset_vars = warn;
+        self
+    }
+    /// Show the description of a registered command while its line is typed
+    /// # Arguments
+    /// * `show` - true to show a hint above the input line, off by default
+    ///
+    /// The hint also lists the arguments given with [`ConsoleWindow::set_command_args`]
+    /// and highlights the one the cursor is in. It never takes the focus and is hidden
+    /// in reverse-i-search.
+    ///
+    /// # Returns
+    /// * `ConsoleBuilder` - the console builder
+    ///
+    pub fn inline_help(mut self, show: bool) -> Self {
+        self.inline_help = show;
+        self
+    }
+    /// Clean up pasted text
+    /// # Arguments
+    /// * `filter` - true, the default, to drop escape sequences and control characters
+    ///   other than newline and tab from pasted text
+    ///
+    /// `\r\n` and `\r` become `\n` either way.
+    ///
+    /// # Returns
+    /// * `ConsoleBuilder` - the console builder
+    ///
+    pub fn paste_filter(mut self, filter: bool) -> Self {
+        self.paste_filter = filter;
+        self
+    }
+    /// Ask before pasting a lot of text
+    /// # Arguments
+    /// * `limit` - pastes with more characters than this wait for `y` to be typed, 0 never
+    ///   asks. It is 10,000 by default
+    ///
+    /// # Returns
+    /// * `ConsoleBuilder` - the console builder
+    ///
+    pub fn paste_limit(mut self, limit: usize) -> Self {
+        self.paste_limit = limit;
+        self
+    }
+    /// Choose what dropping files on the console does
+    /// # Arguments
+    /// * `behavior` - the [`DropBehavior`], by default the paths are typed at the cursor
+    ///
+    /// The border is tinted while files are dragged over the console. Files dropped on a
+    /// web page have no path and are ignored.
+    ///
+    /// # Returns
+    /// * `ConsoleBuilder` - the console builder
+    ///
+    pub fn drop_behavior(mut self, behavior: DropBehavior) -> Self {
+        self.drop_behavior = behavior;
+        self
+    }
+    /// Tell screen readers about new output
+    /// # Arguments
+    /// * `on` - true, the default, to keep track of what was written for screen readers
+    ///
+    /// With the `accesskit` feature new lines are read out from a log region, errors
+    /// from an alert region, and the input is labelled with the prompt. Hosts that don't
+    /// need it can save the bookkeeping.
+    ///
+    /// # Returns
+    /// * `ConsoleBuilder` - the console builder
+    ///
+    pub fn accessibility(mut self, on: bool) -> Self {
+        self.accessibility = on;
+        self
+    }
+    /// Split entered lines on `;` and `&&`
+    /// # Arguments
+    /// * `chain` - true to return the pieces of a line one at a time
+    ///
+    /// Each piece is returned by its own [`ConsoleEvent::Command`] on a later frame. A piece
+    /// after `&&` waits for [`ConsoleWindow::command_finished`] and is skipped if the
+    /// previous one failed. Separators inside quotes are left alone. The history keeps
+    /// the whole line.
+    ///
+    /// # Returns
+    /// * `ConsoleBuilder` - the console builder
+    ///
+    pub fn chain_commands(mut self, chain: bool) -> Self {
+        self.chain_commands = chain;
+        self
+    }
+    /// Let `> file` and `>> file` at the end of a line send the output of the command to a file
+    /// # Arguments
+    /// * `redirect` - false for hosts where `>` means something else, it is on by default
+    ///
+    /// The redirection is removed from the line before it is returned. Everything written
+    /// with [`ConsoleWindow::write`] until the next [`ConsoleWindow::prompt`] or
+    /// [`ConsoleWindow::command_finished`] goes to the file, `>>` appends to it.
+    ///
+    /// There is no file system on the web, so it is always off on wasm32.
+    ///
+    /// # Returns
+    /// * `ConsoleBuilder` - the console builder
+    ///
+    pub fn redirect_output(mut self, redirect: bool) -> Self {
+        self.redirect_output = redirect;
+        self
+    }
+    /// Set a prompt that is worked out each time it is drawn
+    /// # Arguments
+    /// * `template` - the prompt text with placeholders
+    ///
+    /// The placeholders are
+    /// - `{cwd}` the current directory
+    /// - `{cwd_short}` the last part of the current directory
+    /// - `{time:%H:%M}` the local time, `%H`, `%M` and `%S` are replaced. On the web it is
+    ///   the UTC time
+    /// - `{status}` ✓ or ✗ for the result of the last command
+    /// - `{var:NAME}` a variable, see [`ConsoleWindow::set_var`]
+    /// - anything added with [`ConsoleWindow::set_prompt_placeholder`]
+    ///
+    /// `{{` is a literal `{`. This overrides [`ConsoleBuilder::prompt`].
+    ///
+    /// # Returns
+    /// * `ConsoleBuilder` - the console builder
+    ///
+    pub fn prompt_template(mut self, template: &str) -> Self {
+        self.prompt_template = Some(template.to_string());
+        self
+    }
+    /// Give the console a fixed egui id
+    /// # Arguments
+    /// * `source` - anything hashable that is unique among the consoles
+    ///
+    /// Without it each console gets the next id from a counter, so the id depends on the
+    /// order the consoles are made in. Apps that persist their consoles, or make and drop
+    /// them as they go, should always set one so the cursor and focus stay with the right
+    /// console.
+    ///
+    /// # Returns
+    /// * `ConsoleBuilder` - the console builder
+    ///
+    pub fn id_source(mut self, source: impl std::hash::Hash) -> Self {
+        self.id_source = Some(Id::new(source));
+        self
+    }
     /// Build the console window
     /// # Returns
     /// * `ConsoleWindow` - the console window
@@ -648,6 +1629,513 @@ impl ConsoleBuilder {
         cons.history_size = self.history_size;
         cons.scrollback_size = self.scrollback_size;
         cons.tab_quote = self.tab_quote_character;
+        cons.sink_queue.set_limit(self.sink_limit);
+        cons.parse_commands = self.parse_commands;
+        cons.search_mode = self.search_mode;
+        cons.search_case = self.search_case;
+        cons.recall_keys = self.recall_keys;
+        cons.expand_vars = self.expand_vars;
+        cons.warn_unset_vars = self.warn_unset_vars;
+        cons.inline_help = self.inline_help;
+        cons.keep_paste_controls = !self.paste_filter;
+        cons.paste_limit = self.paste_limit;
+        cons.drop_behavior = self.drop_behavior;
+        cons.accessibility_off = !self.accessibility;
+        cons.chain_commands = self.chain_commands;
+        cons.redirect_disabled = !self.redirect_output || cfg!(target_arch = "wasm32");
+        cons.prompt_template = self.prompt_template;
+        cons.id_source = self.id_source;
         cons
     }
 }
+
+#[cfg(test)]
+fn run_frame(ctx: &Context, console: &mut ConsoleWindow, events: Vec<Event>) -> ConsoleEvent {
+    let input = egui::RawInput {
+        events,
+        // ctrl held, as on linux and windows where ctrl-c arrives as a copy
+        modifiers: Modifiers::CTRL | Modifiers::COMMAND,
+        ..Default::default()
+    };
+    let mut msg = ConsoleEvent::None;
+    let _ = ctx.run(input, |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ctx.memory_mut(|mem| mem.request_focus(console.id()));
+            msg = console.draw(ui);
+        });
+    });
+    msg
+}
+
+#[cfg(test)]
+pub(crate) fn key(key: Key) -> Event {
+    key_with(key, Modifiers::NONE)
+}
+
+#[cfg(test)]
+pub(crate) fn key_with(key: Key, modifiers: Modifiers) -> Event {
+    Event::Key {
+        key,
+        physical_key: None,
+        pressed: true,
+        repeat: false,
+        modifiers,
+    }
+}
+
+// ctrl-r as run_frame sends it, starts or steps a history search
+#[cfg(test)]
+fn ctrl_r() -> Event {
+    key_with(Key::R, Modifiers::CTRL | Modifiers::COMMAND)
+}
+
+#[test]
+fn test_enter_and_interrupt_same_frame() {
+    let ctx = Context::default();
+    let mut console = ConsoleBuilder::new().build();
+    run_frame(&ctx, &mut console, vec![]);
+
+    // enter then ctrl-c, the command must not be lost
+    console.text.push_str("ls");
+    let mut seen = vec![run_frame(
+        &ctx,
+        &mut console,
+        vec![key(Key::Enter), Event::Copy],
+    )];
+    seen.push(run_frame(&ctx, &mut console, vec![]));
+    seen.push(run_frame(&ctx, &mut console, vec![]));
+    seen.push(run_frame(&ctx, &mut console, vec![]));
+    assert_eq!(
+        seen,
+        vec![
+            ConsoleEvent::Command("ls".to_string()),
+            ConsoleEvent::HistoryChanged,
+            ConsoleEvent::Interrupt,
+            ConsoleEvent::None
+        ]
+    );
+    console.prompt();
+
+    // ctrl-c then enter, the typed line is abandoned and an empty line entered
+    console.text.push_str("rm");
+    let mut seen = vec![run_frame(
+        &ctx,
+        &mut console,
+        vec![Event::Copy, key(Key::Enter)],
+    )];
+    seen.push(run_frame(&ctx, &mut console, vec![]));
+    seen.push(run_frame(&ctx, &mut console, vec![]));
+    assert_eq!(
+        seen,
+        vec![
+            ConsoleEvent::Interrupt,
+            ConsoleEvent::Command(String::new()),
+            ConsoleEvent::HistoryChanged,
+        ]
+    );
+    assert!(console.text.contains(">> rm^C\n>> "));
+}
+
+#[test]
+fn test_set_prompt() {
+    let ctx = Context::default();
+    let mut console = ConsoleBuilder::new().build();
+    run_frame(&ctx, &mut console, vec![]);
+    assert_eq!(console.text, ">> ");
+
+    // nothing typed, replaced in place
+    console.set_prompt("$ ");
+    assert_eq!(console.text, "$ ");
+    assert_eq!(console.get_last_line(), "");
+
+    // something typed, the next line gets it
+    console.text.push_str("ls");
+    console.set_prompt("% ");
+    assert_eq!(console.get_last_line(), "ls");
+    console.write("out");
+    console.prompt();
+    assert_eq!(console.text, "$ ls\nout\n% ");
+
+    // leaving reverse search shows the new prompt
+    console.enter_search_mode(console.text.chars().count());
+    console.set_prompt("> ");
+    assert_eq!(console.prompt_len, 2);
+    console.set_prompt("long> ");
+    assert_eq!(console.prompt_len, 2);
+    console.exit_search_mode();
+    assert!(console.text.ends_with("\nlong> "));
+    assert_eq!(console.prompt_len, 6);
+}
+
+#[cfg(feature = "persistence")]
+#[test]
+fn test_persistence_round_trip() {
+    let mut console = ConsoleBuilder::new()
+        .prompt("$ ")
+        .history_size(5)
+        .scrollback_size(50)
+        .build();
+    console.load_history("one\ntwo".lines());
+    console.set_alias("ll", "dir -l");
+    console.write("not kept");
+
+    let saved = ron::to_string(&console).unwrap();
+    let restored: ConsoleWindow = ron::from_str(&saved).unwrap();
+    assert_eq!(restored.get_history(), vec!["one", "two"]);
+    assert_eq!(restored.prompt, "$ ");
+    assert_eq!(restored.history_size, 5);
+    assert_eq!(restored.scrollback_size, 50);
+    assert_eq!(restored.aliases()["ll"], "dir -l");
+    assert!(restored.text.is_empty());
+    assert_ne!(restored.id(), console.id());
+
+    let console = ConsoleBuilder::new().id_source("main").build();
+    let restored: ConsoleWindow = ron::from_str(&ron::to_string(&console).unwrap()).unwrap();
+    assert_eq!(restored.id(), console.id());
+}
+
+#[test]
+fn test_request_focus() {
+    let ctx = Context::default();
+    let mut console = ConsoleBuilder::new().build();
+    let frame = |console: &mut ConsoleWindow| {
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                console.draw(ui);
+            });
+        });
+    };
+    frame(&mut console);
+    assert!(!console.has_focus(&ctx));
+    console.request_focus(&ctx);
+    frame(&mut console);
+    assert!(console.has_focus(&ctx));
+}
+
+#[test]
+fn test_draw_full() {
+    let ctx = Context::default();
+    let mut console = ConsoleBuilder::new().build();
+    let mut output = None;
+    let _ = ctx.run(egui::RawInput::default(), |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            output = Some(console.draw_full(ui));
+        });
+    });
+    let output = output.unwrap();
+    assert_eq!(output.event, ConsoleEvent::None);
+    assert_eq!(output.response.id, console.id());
+    assert!(output.response.rect.width() > 0.0);
+    assert!(!output.has_focus);
+    assert!(output.scrolled_to_bottom);
+}
+
+#[test]
+fn test_id_source() {
+    let build = |name: &str| ConsoleBuilder::new().id_source(("console", name)).build();
+    let (a, b) = (build("a"), build("b"));
+    let (b2, a2) = (build("b"), build("a"));
+    assert_eq!(a.id(), a2.id());
+    assert_eq!(b.id(), b2.id());
+    assert_ne!(a.id(), b.id());
+    // without a source every console is different
+    assert_ne!(
+        ConsoleBuilder::new().build().id(),
+        ConsoleBuilder::new().build().id()
+    );
+}
+
+#[test]
+fn test_two_lines_in_one_frame() {
+    let ctx = Context::default();
+    let mut console = ConsoleBuilder::new().build();
+    run_frame(&ctx, &mut console, vec![]);
+
+    let events = vec![
+        Event::Text("one".to_string()),
+        key(Key::Enter),
+        Event::Text("two".to_string()),
+        key(Key::Enter),
+        Event::Paste("three\r\nfour".to_string()),
+    ];
+    let mut commands = vec![];
+    let mut msg = run_frame(&ctx, &mut console, events);
+    for _ in 0..8 {
+        if let ConsoleEvent::Command(command) = msg {
+            commands.push(command);
+            console.prompt();
+        }
+        msg = run_frame(&ctx, &mut console, vec![]);
+    }
+    assert_eq!(commands, vec!["one", "two", "three"]);
+    assert_eq!(console.get_history(), vec!["one", "two", "three"]);
+    assert_eq!(console.text, ">> one\n>> two\n>> three\n>> four");
+}
+
+#[test]
+fn test_run_command() {
+    let ctx = Context::default();
+    let mut console = ConsoleBuilder::new().build();
+    // before the first frame it waits for the prompt
+    console.run_command("first");
+    assert_eq!(
+        run_frame(&ctx, &mut console, vec![]),
+        ConsoleEvent::Command("first".to_string())
+    );
+    console.prompt();
+    run_frame(&ctx, &mut console, vec![]);
+
+    run_frame(&ctx, &mut console, vec![Event::Text("half".to_string())]);
+    console.run_command("reload");
+    let mut commands = vec![];
+    for _ in 0..4 {
+        if let ConsoleEvent::Command(command) = run_frame(&ctx, &mut console, vec![]) {
+            commands.push(command);
+            console.prompt();
+        }
+    }
+    assert_eq!(commands, vec!["reload"]);
+    assert_eq!(console.get_history(), vec!["first", "reload"]);
+    assert_eq!(console.text, ">> first\n>> reload\n>> half");
+
+    console.inject_silently("quiet");
+    assert_eq!(
+        run_frame(&ctx, &mut console, vec![]),
+        ConsoleEvent::Command("quiet".to_string())
+    );
+    assert_eq!(console.get_history(), vec!["first", "reload"]);
+    assert!(console.text.ends_with(">> half"));
+}
+
+#[test]
+fn test_fuzzy_history_search() {
+    let ctx = Context::default();
+    let mut console = ConsoleBuilder::new().build();
+    console.load_history("git commit -m first\ngit checkout main\nls".lines());
+    run_frame(&ctx, &mut console, vec![]);
+
+    run_frame(&ctx, &mut console, vec![ctrl_r()]);
+    run_frame(&ctx, &mut console, vec![]);
+    // one at a time, the cursor has to follow the prompt as the position appears
+    for ch in ["g", "c", "m"] {
+        run_frame(&ctx, &mut console, vec![Event::Text(ch.to_string())]);
+    }
+    assert!(console
+        .text
+        .ends_with("(reverse-i-search 1/2) gcm:git commit -m first"));
+    run_frame(&ctx, &mut console, vec![ctrl_r()]);
+    assert!(console
+        .text
+        .ends_with("(reverse-i-search 2/2) gcm:git checkout main"));
+    run_frame(&ctx, &mut console, vec![key(Key::ArrowUp)]);
+    assert!(console
+        .text
+        .ends_with("(reverse-i-search 1/2) gcm:git commit -m first"));
+    run_frame(&ctx, &mut console, vec![key(Key::ArrowDown)]);
+    assert_eq!(
+        run_frame(&ctx, &mut console, vec![key(Key::Enter)]),
+        ConsoleEvent::Command("git checkout main".to_string())
+    );
+    // the accepted match is echoed after the prompt
+    assert!(console.text.ends_with(">> git checkout main"));
+
+    // tab takes the match onto the input line without entering it
+    console.prompt();
+    run_frame(&ctx, &mut console, vec![]);
+    run_frame(&ctx, &mut console, vec![ctrl_r()]);
+    run_frame(&ctx, &mut console, vec![Event::Text("ls".to_string())]);
+    run_frame(&ctx, &mut console, vec![key(Key::Tab)]);
+    assert!(console.text.ends_with("\n>> ls"));
+    assert!(console.search_partial.is_none());
+}
+
+#[test]
+fn test_abort_search() {
+    let ctx = Context::default();
+    let mut console = ConsoleBuilder::new().build();
+    console.load_history("git status\nls".lines());
+    run_frame(&ctx, &mut console, vec![]);
+    let ctrl = |key| key_with(key, Modifiers::CTRL | Modifiers::COMMAND);
+    run_frame(&ctx, &mut console, vec![Event::Text("cargo b".to_string())]);
+    run_frame(&ctx, &mut console, vec![key(Key::ArrowLeft)]);
+
+    for abort in [ctrl(Key::G), key(Key::Escape)] {
+        run_frame(&ctx, &mut console, vec![ctrl_r()]);
+        run_frame(&ctx, &mut console, vec![Event::Text("git".to_string())]);
+        assert!(console.text.ends_with("git:git status"));
+        run_frame(&ctx, &mut console, vec![abort]);
+        run_frame(&ctx, &mut console, vec![]);
+        assert_eq!(console.text, ">> cargo b");
+        assert_eq!(console.prompt, ">> ");
+        // the cursor is back before the b
+        let cursor = TextEdit::load_state(&ctx, console.id())
+            .and_then(|state| state.cursor.char_range())
+            .map(|range| range.primary.index);
+        assert_eq!(cursor, Some(">> cargo ".len()));
+    }
+    // a second exit has no saved prompt and must not panic
+    console.exit_search_mode();
+    assert_eq!(console.prompt, ">> ");
+}
+
+#[test]
+fn test_search_twice() {
+    let ctx = Context::default();
+    let mut console = ConsoleBuilder::new().prompt("$ ").build();
+    console.load_history("ls".lines());
+    run_frame(&ctx, &mut console, vec![]);
+    run_frame(&ctx, &mut console, vec![ctrl_r()]);
+    run_frame(&ctx, &mut console, vec![ctrl_r()]);
+    // entering again keeps the saved prompt
+    console.enter_search_mode(0);
+    run_frame(&ctx, &mut console, vec![key(Key::Escape)]);
+    assert_eq!(console.prompt, "$ ");
+    assert_eq!(console.text, "$ ");
+
+    // with nothing saved the builder prompt comes back
+    console.prompt = "(reverse-i-search) :".to_string();
+    console.exit_search_mode();
+    assert_eq!(console.prompt, "$ ");
+}
+
+#[test]
+fn test_search_multibyte_query() {
+    let ctx = Context::default();
+    let mut console = ConsoleBuilder::new().build();
+    console.load_history("echo 日本語 ok\nls".lines());
+    run_frame(&ctx, &mut console, vec![]);
+    let cursor = |console: &ConsoleWindow| {
+        TextEdit::load_state(&ctx, console.id())
+            .and_then(|state| state.cursor.char_range())
+            .map(|range| range.primary.index)
+    };
+
+    for (query, line) in [
+        ("héllo", "(reverse-i-search) héllo:"),
+        ("日本語", "(reverse-i-search 1/1) 日本語:echo 日本語 ok"),
+    ] {
+        console.prompt();
+        run_frame(&ctx, &mut console, vec![ctrl_r()]);
+        for ch in query.chars() {
+            run_frame(&ctx, &mut console, vec![Event::Text(ch.to_string())]);
+        }
+        assert!(console.text.ends_with(line));
+        let (start, end) = console.search_bounds();
+        assert_eq!(end - start, query.chars().count());
+
+        // arrow right stops at the end of the query
+        run_frame(&ctx, &mut console, vec![key(Key::ArrowRight)]);
+        run_frame(&ctx, &mut console, vec![]);
+        assert_eq!(cursor(&console), Some(end));
+
+        // backspace at the start of the query leaves the prompt alone
+        let left = vec![key(Key::ArrowLeft); query.chars().count() + 2];
+        run_frame(&ctx, &mut console, left);
+        run_frame(&ctx, &mut console, vec![]);
+        assert_eq!(cursor(&console), Some(start));
+        run_frame(&ctx, &mut console, vec![key(Key::Backspace)]);
+        run_frame(&ctx, &mut console, vec![]);
+        assert!(console.text.ends_with(line));
+        assert_eq!(console.search_partial.as_deref(), Some(query));
+
+        run_frame(&ctx, &mut console, vec![key(Key::Escape)]);
+    }
+}
+
+#[test]
+fn test_recall_search() {
+    let ctx = Context::default();
+    let mut console = ConsoleBuilder::new().build();
+    console.load_history("git commit\ngit checkout\nls".lines());
+    run_frame(&ctx, &mut console, vec![]);
+    let alt_n = key_with(Key::N, Modifiers::ALT);
+    let alt_p = key_with(Key::P, Modifiers::ALT);
+    run_frame(&ctx, &mut console, vec![ctrl_r()]);
+    run_frame(&ctx, &mut console, vec![Event::Text("git".to_string())]);
+    run_frame(&ctx, &mut console, vec![key(Key::Tab)]);
+    assert!(console.text.ends_with(">> git checkout"));
+
+    // the same search again at the next match
+    run_frame(&ctx, &mut console, vec![alt_n.clone()]);
+    assert!(console
+        .text
+        .ends_with("(reverse-i-search 2/2) git:git commit"));
+    run_frame(&ctx, &mut console, vec![key(Key::Tab)]);
+    assert!(console.text.ends_with(">> git commit"));
+    run_frame(&ctx, &mut console, vec![alt_p]);
+    assert!(console
+        .text
+        .ends_with("(reverse-i-search 1/2) git:git checkout"));
+    run_frame(&ctx, &mut console, vec![key(Key::Tab)]);
+
+    // an edited line forgets the search
+    run_frame(&ctx, &mut console, vec![Event::Text(" -b".to_string())]);
+    run_frame(&ctx, &mut console, vec![alt_n]);
+    assert!(console.search_partial.is_none());
+    assert!(console.text.ends_with(">> git checkout -b"));
+}
+
+#[test]
+fn test_scrollback_burst() {
+    let mut console = ConsoleBuilder::new().scrollback_size(1000).build();
+    for i in 0..200_000 {
+        console.write(&format!("line {}", i));
+    }
+    let lines: Vec<&str> = console.text.lines().collect();
+    // dropped in batches, not on every write
+    assert!(lines.len() >= 1000 && lines.len() <= 1100);
+    assert_eq!(lines.len(), console.line_count);
+    assert_eq!(lines.last(), Some(&"line 199999"));
+    let first: usize = lines[0]["line ".len()..].parse().unwrap();
+    assert_eq!(first + lines.len(), 200_000);
+}
+
+#[test]
+fn test_scrollback_keeps_input() {
+    let ctx = Context::default();
+    let mut console = ConsoleBuilder::new().scrollback_size(10).build();
+    console.load_history("git status\nls".lines());
+    run_frame(&ctx, &mut console, vec![]);
+    let sink = console.sink();
+    let flood = |console: &mut ConsoleWindow| {
+        for i in 0..50 {
+            sink.above_prompt(&format!("line {}", i));
+        }
+        run_frame(&ctx, console, vec![]);
+        assert!(console.text.lines().count() <= 11);
+        assert!(console.text.contains("\nline 49\n"));
+    };
+    run_frame(&ctx, &mut console, vec![Event::Text("cargo b".to_string())]);
+    flood(&mut console);
+    assert_eq!(console.get_last_line(), "cargo b");
+
+    // walking the history carries on where it was
+    run_frame(&ctx, &mut console, vec![key(Key::ArrowUp)]);
+    flood(&mut console);
+    assert_eq!(console.get_last_line(), "ls");
+    run_frame(&ctx, &mut console, vec![key(Key::ArrowUp)]);
+    assert_eq!(console.get_last_line(), "git status");
+    assert_eq!(console.history_cursor, Some(0));
+
+    run_frame(&ctx, &mut console, vec![key(Key::Escape)]);
+    run_frame(&ctx, &mut console, vec![ctrl_r()]);
+    run_frame(&ctx, &mut console, vec![Event::Text("sta".to_string())]);
+    flood(&mut console);
+    assert!(console.search_partial.is_some());
+    assert!(console.text.ends_with("sta:git status"));
+}
+
+#[test]
+fn test_scrollback_trailing_newline() {
+    let mut console = ConsoleBuilder::new().scrollback_size(10).build();
+    for i in 0..50 {
+        console.write(&format!("line {}", i));
+    }
+    assert!(console.text.ends_with("\nline 49"));
+    console.draw_prompt();
+    console.write("done\n");
+    // the cut lands between lines and the newline at the end stays
+    assert!(console.text.ends_with("\ndone\n"));
+    assert!(console.text.starts_with("line "));
+    assert_eq!(console.text.lines().count(), console.line_count - 1);
+}
@@ -0,0 +1,136 @@
+use crate::{ConsoleEvent, ConsoleWindow};
+use egui::{Rect, StrokeKind, Ui};
+
+/// What happens when files are dropped on the console
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum DropBehavior {
+    /// The paths are typed at the cursor, quoted like tab completion does
+    #[default]
+    InsertPath,
+    /// A [`ConsoleEvent::FileDropped`] is returned for each file
+    Emit,
+    /// Both
+    Both,
+}
+
+impl ConsoleWindow {
+    // tint the border while files are dragged over the console and take them when dropped
+    pub(crate) fn drop_ui(&mut self, ui: &Ui, rect: Rect) {
+        let (hovering, dropped, pointer) = ui.input(|input| {
+            (
+                !input.raw.hovered_files.is_empty(),
+                input.raw.dropped_files.clone(),
+                input.pointer.hover_pos(),
+            )
+        });
+        // the pointer is not always known while dragging, then the focused console takes them
+        let over = match pointer {
+            Some(pos) => rect.contains(pos),
+            None => self.has_focus(ui.ctx()),
+        };
+        if !over {
+            return;
+        }
+        if hovering {
+            let stroke = egui::Stroke::new(2.0, ui.visuals().selection.bg_fill);
+            ui.painter()
+                .rect_stroke(rect, 2.0, stroke, StrokeKind::Inside);
+        }
+        // on the web a dropped file has a name but no path
+        let paths: Vec<_> = dropped.into_iter().filter_map(|file| file.path).collect();
+        if paths.is_empty() {
+            return;
+        }
+        let insert = matches!(
+            self.drop_behavior,
+            DropBehavior::InsertPath | DropBehavior::Both
+        );
+        if insert && self.search_partial.is_none() && !self.is_busy() {
+            let mut typed = paths
+                .iter()
+                .map(|path| quote_path(&path.display().to_string(), self.tab_quote))
+                .collect::<Vec<_>>()
+                .join(" ");
+            typed.push(' ');
+            let mut cursor = egui::TextEdit::load_state(ui.ctx(), self.id())
+                .and_then(|state| state.cursor.char_range())
+                .map_or(self.text.chars().count(), |range| range.primary.index);
+            let mut entered = false;
+            self.type_text(&typed, &mut cursor, &mut entered);
+        }
+        if matches!(self.drop_behavior, DropBehavior::Emit | DropBehavior::Both) {
+            self.events
+                .extend(paths.into_iter().map(ConsoleEvent::FileDropped));
+        }
+        ui.ctx().request_repaint();
+    }
+}
+
+// quote a path the way tab completion does, single quotes keep it as it is, inside double
+// quotes a backslash escapes, so backslashes and double quotes get one
+pub(crate) fn quote_path(path: &str, quote: char) -> String {
+    if !path.contains([' ', '\t', '"', '\'']) {
+        return path.to_string();
+    }
+    if quote == '\'' && !path.contains('\'') {
+        return format!("'{}'", path);
+    }
+    format!("\"{}\"", path.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[test]
+fn test_quote_path() {
+    use crate::command::split_args;
+    assert_eq!(quote_path("/tmp/a.txt", '\''), "/tmp/a.txt");
+    assert_eq!(quote_path("/tmp/my file", '\''), "'/tmp/my file'");
+    assert_eq!(quote_path("/tmp/it's", '\''), "\"/tmp/it's\"");
+    let both = r#"/tmp/it's "x" \y"#;
+    assert_eq!(split_args(&quote_path(both, '"')).unwrap(), vec![both]);
+    // backslashes survive double quotes
+    for path in [r"C:\Program Files\x.sav", r"C:\Bob's Files\x.sav"] {
+        for quote in ['"', '\''] {
+            assert_eq!(split_args(&quote_path(path, quote)).unwrap(), vec![path]);
+        }
+    }
+}
+
+#[test]
+fn test_drop_files() {
+    let ctx = egui::Context::default();
+    let mut console = crate::ConsoleBuilder::new()
+        .drop_behavior(DropBehavior::Both)
+        .build();
+    let frame = |console: &mut ConsoleWindow, dropped_files: Vec<egui::DroppedFile>| {
+        let mut event = ConsoleEvent::None;
+        let _ = ctx.run(
+            egui::RawInput {
+                dropped_files,
+                ..Default::default()
+            },
+            |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ctx.memory_mut(|mem| mem.request_focus(console.id()));
+                    event = console.draw(ui);
+                });
+            },
+        );
+        event
+    };
+    let file = |path: &str| egui::DroppedFile {
+        path: Some(path.into()),
+        ..Default::default()
+    };
+    frame(&mut console, vec![]);
+    frame(&mut console, vec![file("/tmp/my save.dat"), file("/tmp/b")]);
+    assert_eq!(console.text, ">> '/tmp/my save.dat' /tmp/b ");
+    assert_eq!(
+        frame(&mut console, vec![]),
+        ConsoleEvent::FileDropped("/tmp/my save.dat".into())
+    );
+    assert_eq!(
+        frame(&mut console, vec![]),
+        ConsoleEvent::FileDropped("/tmp/b".into())
+    );
+}
@@ -0,0 +1,321 @@
+use crate::ConsoleWindow;
+use egui::{
+    text::{CCursor, LayoutJob},
+    Align, Color32, Key, Modifiers, TextEdit, TextFormat, Ui,
+};
+use std::ops::Range;
+
+/// State of the ctrl-f find bar, only present while the bar is open
+#[derive(Debug, Default)]
+pub(crate) struct FindBar {
+    pub(crate) query: String,
+    pub(crate) case_sensitive: bool,
+    // byte ranges into the console text
+    pub(crate) matches: Vec<Range<usize>>,
+    pub(crate) current: usize,
+    // the view needs to be moved to the current match
+    pub(crate) scroll_pending: bool,
+    // the query input needs to grab focus
+    pub(crate) focus_pending: bool,
+    // the lines with a match are listed under the bar
+    pub(crate) list: bool,
+}
+
+// most matching lines shown under the find bar before it scrolls
+const LISTED_LINES: f32 = 8.0;
+
+impl ConsoleWindow {
+    pub(crate) fn open_find(&mut self) {
+        let find = self.find.get_or_insert_with(FindBar::default);
+        find.focus_pending = true;
+        find.scroll_pending = true;
+    }
+
+    // ctrl-shift-r, the find bar with the matching lines listed
+    pub(crate) fn open_find_list(&mut self) {
+        self.open_find();
+        if let Some(find) = self.find.as_mut() {
+            find.list = true;
+        }
+    }
+
+    pub(crate) fn close_find(&mut self, ui: &Ui) {
+        self.find = None;
+        self.force_cursor_to_end = true;
+        ui.ctx().memory_mut(|mem| mem.request_focus(self.id()));
+    }
+
+    // draws the find bar above the console and handles its keys
+    pub(crate) fn find_ui(&mut self, ui: &mut Ui) {
+        let id = self.id().with("find");
+        let case = self.search_case;
+        let Some(find) = self.find.as_mut() else {
+            return;
+        };
+        let mut close = false;
+        ui.horizontal(|ui| {
+            ui.label("Find:");
+            let response = ui.add(
+                TextEdit::singleline(&mut find.query)
+                    .id(id)
+                    .desired_width(200.0),
+            );
+            if find.focus_pending {
+                response.request_focus();
+                find.focus_pending = false;
+            }
+            let case_changed = ui
+                .checkbox(&mut find.case_sensitive, "Aa")
+                .on_hover_text("match case, otherwise the history search case mode is used")
+                .changed();
+            ui.checkbox(&mut find.list, "Lines");
+            if response.changed() || case_changed {
+                find.current = 0;
+                find.scroll_pending = true;
+            }
+
+            // the console text can change under us, so always rescan
+            let case_sensitive = find.case_sensitive || !case.ignore_case(&find.query);
+            find.matches = find_matches(&self.text, &find.query, case_sensitive);
+            if find.current >= find.matches.len() {
+                find.current = 0;
+            }
+            let count = if find.matches.is_empty() {
+                "0/0".to_string()
+            } else {
+                format!("{}/{}", find.current + 1, find.matches.len())
+            };
+            ui.label(count);
+
+            // a singleline edit gives up focus on enter and escape
+            if response.lost_focus() {
+                if ui.input(|inp| inp.key_pressed(Key::Escape)) {
+                    close = true;
+                } else if ui.input(|inp| inp.key_pressed(Key::Enter)) {
+                    if !find.matches.is_empty() {
+                        let len = find.matches.len();
+                        find.current = if ui.input(|inp| inp.modifiers.shift) {
+                            (find.current + len - 1) % len
+                        } else {
+                            (find.current + 1) % len
+                        };
+                        find.scroll_pending = true;
+                    }
+                    response.request_focus();
+                }
+            }
+            if response.has_focus()
+                && ui.input_mut(|inp| inp.consume_key(Modifiers::COMMAND, Key::F))
+            {
+                close = true;
+            }
+        });
+        if find.list && !find.matches.is_empty() {
+            let row =
+                ui.text_style_height(&egui::TextStyle::Monospace) + ui.spacing().item_spacing.y;
+            let mut clicked = None;
+            egui::ScrollArea::vertical()
+                .id_salt(id.with("lines"))
+                .max_height(row * LISTED_LINES)
+                .auto_shrink([false, true])
+                .show_rows(ui, row, find.matches.len(), |ui, rows| {
+                    // only the lines in view are looked up
+                    for i in rows {
+                        let (number, line, range) = match_line(&self.text, &find.matches[i]);
+                        let label = format!("{:>5}: ", number);
+                        let highlight = (
+                            range.start + label.len()..range.end + label.len(),
+                            i == find.current,
+                        );
+                        let job =
+                            highlight_layout(ui, &(label + line), &[highlight], f32::INFINITY);
+                        if ui.selectable_label(i == find.current, job).clicked() {
+                            clicked = Some(i);
+                        }
+                    }
+                });
+            if let Some(i) = clicked {
+                find.current = i;
+                find.scroll_pending = true;
+            }
+        }
+        if close {
+            self.close_find(ui);
+        }
+    }
+
+    // highlighted ranges for the layouter, the bool marks the current match
+    pub(crate) fn find_highlights(&self) -> Vec<(Range<usize>, bool)> {
+        match &self.find {
+            Some(find) => find
+                .matches
+                .iter()
+                .enumerate()
+                .map(|(i, range)| (range.clone(), i == find.current))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    // move the view so the current match is visible
+    pub(crate) fn scroll_to_find_match(
+        &mut self,
+        ui: &Ui,
+        output: &egui::text_edit::TextEditOutput,
+    ) {
+        let Some(find) = self.find.as_mut() else {
+            return;
+        };
+        if !find.scroll_pending {
+            return;
+        }
+        find.scroll_pending = false;
+        if let Some(range) = find.matches.get(find.current) {
+            let Some(before) = self.text.get(..range.start) else {
+                return;
+            };
+            let cursor = CCursor::new(before.chars().count());
+            let rect = output
+                .galley
+                .pos_from_cursor(cursor)
+                .translate(output.galley_pos.to_vec2());
+            ui.scroll_to_rect(rect, Some(Align::Center));
+        }
+    }
+}
+
+// lay out the console text with the find matches given a background
+pub(crate) fn highlight_layout(
+    ui: &Ui,
+    text: &str,
+    highlights: &[(Range<usize>, bool)],
+    wrap_width: f32,
+) -> LayoutJob {
+    let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+    let color = ui.visuals().widgets.inactive.text_color();
+    let plain = TextFormat::simple(font_id.clone(), color);
+    let selection = ui.visuals().selection.bg_fill;
+
+    let mut job = LayoutJob::default();
+    job.wrap.max_width = wrap_width;
+    let mut pos = 0;
+    for (range, current) in highlights {
+        // the text may have been edited since the matches were found
+        if range.start < pos
+            || range.end > text.len()
+            || !text.is_char_boundary(range.start)
+            || !text.is_char_boundary(range.end)
+        {
+            continue;
+        }
+        job.append(&text[pos..range.start], 0.0, plain.clone());
+        let background = if *current {
+            selection
+        } else {
+            selection.gamma_multiply(0.4)
+        };
+        job.append(
+            &text[range.clone()],
+            0.0,
+            TextFormat {
+                background,
+                color: if *current { Color32::WHITE } else { color },
+                ..plain.clone()
+            },
+        );
+        pos = range.end;
+    }
+    job.append(&text[pos..], 0.0, plain);
+    job
+}
+
+// the line number, counted from 1, and the line a match is on, with the match range in the line
+fn match_line<'a>(text: &'a str, range: &Range<usize>) -> (usize, &'a str, Range<usize>) {
+    let start = text[..range.start].rfind('\n').map_or(0, |off| off + 1);
+    let end = text[range.end..]
+        .find('\n')
+        .map_or(text.len(), |off| range.end + off);
+    let number = text[..start].matches('\n').count() + 1;
+    (
+        number,
+        &text[start..end],
+        range.start - start..range.end - start,
+    )
+}
+
+// return the byte ranges of all non overlapping occurrences of query
+pub(crate) fn find_matches(text: &str, query: &str, case_sensitive: bool) -> Vec<Range<usize>> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+    if case_sensitive {
+        return text
+            .match_indices(query)
+            .map(|(start, m)| start..start + m.len())
+            .collect();
+    }
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    let mut matches = Vec::new();
+    let mut next_start = 0;
+    for (start, _) in text.char_indices() {
+        if start < next_start {
+            continue;
+        }
+        if let Some(len) = match_len(&text[start..], &query) {
+            matches.push(start..start + len);
+            next_start = start + len;
+        }
+    }
+    matches
+}
+
+// length in bytes of text that matches the (lowercased) query at the start of text
+fn match_len(text: &str, query: &[char]) -> Option<usize> {
+    let mut query = query.iter();
+    let mut want = query.next();
+    for (idx, ch) in text.char_indices() {
+        for lower in ch.to_lowercase() {
+            match want {
+                Some(qc) if *qc == lower => want = query.next(),
+                _ => return None,
+            }
+        }
+        if want.is_none() {
+            return Some(idx + ch.len_utf8());
+        }
+    }
+    None
+}
+
+#[test]
+fn test_find_matches() {
+    let text = "error 42\nok\nERROR 43\nerror";
+    assert_eq!(find_matches(text, "error", true), vec![0..5, 21..26]);
+    assert_eq!(
+        find_matches(text, "error", false),
+        vec![0..5, 12..17, 21..26]
+    );
+    assert_eq!(find_matches(text, "", false), Vec::<Range<usize>>::new());
+    assert_eq!(
+        find_matches(text, "missing", false),
+        Vec::<Range<usize>>::new()
+    );
+
+    // matches dont overlap
+    assert_eq!(find_matches("aaaa", "aa", false), vec![0..2, 2..4]);
+
+    // byte ranges are correct for multi byte text
+    let text = "héllo HÉLLO";
+    let found = find_matches(text, "héllo", false);
+    assert_eq!(found.len(), 2);
+    assert_eq!(&text[found[1].clone()], "HÉLLO");
+}
+
+#[test]
+fn test_match_line() {
+    let text = "building\nwarning: unused\nok";
+    let found = find_matches(text, "unused", true);
+    assert_eq!(match_line(text, &found[0]), (2, "warning: unused", 9..15));
+    assert_eq!(match_line(text, &(0..5)), (1, "building", 0..5));
+    assert_eq!(match_line(text, &(25..27)), (3, "ok", 0..2));
+}
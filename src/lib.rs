@@ -1,63 +1,124 @@
-/// A console window for egui / eframe applications
-///
-/// [Egui / eframe ]: <https://github.com/emilk/egui>
-///
-/// # Example
-///
-/// You need a [`ConsoleWindow`] instance in your egui App
-/// ```ignore
-///pub struct ConsoleDemo {
-///     ...
-///    console: ConsoleWindow,
-///}
-/// ```
-/// Then in the construction phase use [`ConsoleBuilder`] to create a new ConsoleWindow
-/// ```ignore
-/// impl Default for ConsoleDemo {
-///    fn default() -> Self {
-///       Self {
-///          ...
-///         console: ConsoleBuilder::new().prompt(">> ").history_size(20).build()
-///      }
-///    }
-/// }
-/// ```
-///
-/// Now in the egui update callback you must [`ConsoleWindow::draw`] the console in a host container, typically an egui Window
-///
-/// ```ignore
-///  let mut console_response: ConsoleEvent = ConsoleEvent::None;
-///  egui::Window::new("Console Window")
-///      .default_height(500.0)
-///      .resizable(true)
-///      .show(ctx, |ui| {
-///        console_response = self.console.draw(ui);
-///  });
-///```
-///
-/// The draw method returns a [`ConsoleEvent`] that you can use to respond to user input. If the user entered a command then you can hndle that command as you like.
-/// The code here simply echos the command back to the user and reissues the prompt.
-///
-///```ignore
-/// if let ConsoleEvent::Command(command) = console_response {
-///    self.console.print(format!("You entered: {}", command));
-///    self.console.prompt();
-/// }
-///
-///```
-///
-///
-///#  Command history
-///
-/// - ctrl-r searches the command history
-/// - up and down arrow walk though the command history
-///
-/// If you want the command history to be automatically persisted you need to enable the persistence feature. This will use the eframe storage to save the command history between sessions.
-///
-/// Alternatively you can use [`ConsoleWindow::load_history`] and [`ConsoleWindow::get_history`] to manually save and load the command history.    
-#[warn(missing_docs)]
+//! A console window for egui / eframe applications
+//!
+//! [Egui / eframe ]: <https://github.com/emilk/egui>
+//!
+//! # Example
+//!
+//! You need a [`ConsoleWindow`] instance in your egui App
+//! ```ignore
+//!pub struct ConsoleDemo {
+//!     ...
+//!    console: ConsoleWindow,
+//!}
+//! ```
+//! Then in the construction phase use [`ConsoleBuilder`] to create a new ConsoleWindow
+//! ```ignore
+//! impl Default for ConsoleDemo {
+//!    fn default() -> Self {
+//!       Self {
+//!          ...
+//!         console: ConsoleBuilder::new().prompt(">> ").history_size(20).build()
+//!      }
+//!    }
+//! }
+//! ```
+//!
+//! Now in the egui update callback you must [`ConsoleWindow::draw`] the console in a host container, typically an egui Window
+//!
+//! ```ignore
+//!  let mut console_response: ConsoleEvent = ConsoleEvent::None;
+//!  egui::Window::new("Console Window")
+//!      .default_height(500.0)
+//!      .resizable(true)
+//!      .show(ctx, |ui| {
+//!        console_response = self.console.draw(ui);
+//!  });
+//!```
+//!
+//! The draw method returns a [`ConsoleEvent`] that you can use to respond to user input. If the user entered a command then you can hndle that command as you like.
+//! The code here simply echos the command back to the user and reissues the prompt.
+//!
+//!```ignore
+//! if let ConsoleEvent::Command(command) = console_response {
+//!    self.console.print(format!("You entered: {}", command));
+//!    self.console.prompt();
+//! }
+//!
+//!```
+//!
+//!
+//!#  Command history
+//!
+//! - ctrl-r searches the command history, `gcm` finds `git commit -m`. Prefix matches come first, then
+//!   substrings, then the typed characters in order. The best matches are listed above the input line,
+//!   ctrl-r or down steps to the next one and up to the previous one. Enter runs the match, tab puts it on
+//!   the input line to edit. Escape or ctrl-g leave the search and put back what was typed before it.
+//!   Alt-r switches between plain, fuzzy and, with the `regex` feature, regular expression matching.
+//!   Case is ignored unless the search has an upper case letter, see
+//!   [`ConsoleBuilder::history_case_mode`]
+//! - after tab took a match, alt-n and alt-p open the same search again at the next or previous match,
+//!   see [`ConsoleBuilder::history_recall_keys`]
+//! - up and down arrow walk though the command history
+//!
+//! If you want the command history to be automatically persisted you need to enable the persistence feature. This will use the eframe storage to save the command history between sessions.
+//!
+//! Give a persisted console a fixed id with [`ConsoleBuilder::id_source`] so its egui state survives the restore.
+//!
+//! Alternatively you can use [`ConsoleWindow::load_history`] and [`ConsoleWindow::get_history`] to manually save and load the command history.    
+//!
+//!#  Other keys
+//!
+//! - ctrl-c abandons the line being typed and returns [`ConsoleEvent::Interrupt`], if text is selected it copies instead
+//! - ctrl-d on an empty line returns [`ConsoleEvent::Eof`]
+//! - dropping files on the console types their paths, see [`ConsoleBuilder::drop_behavior`]
+//! - pasted text loses control characters and escape sequences, a paste of more than 10,000 characters
+//!   waits for `y`, see [`ConsoleBuilder::paste_limit`]
+//! - ctrl-l clears the screen and returns [`ConsoleEvent::Cleared`]
+//! - page up and page down scroll the view by a page, ctrl-home and ctrl-end jump to the top and bottom
+//!
+//!#  Find in output
+//!
+//! - ctrl-f opens a find bar above the console that searches all of the scrollback
+//! - ctrl-shift-r opens it with the lines that match listed under it with their line numbers, clicking
+//!   one jumps to it
+//! - enter and shift-enter jump to the next and previous match, escape closes the bar
+//! - case is ignored as set with [`ConsoleBuilder::history_case_mode`] unless `Aa` is ticked
+#![warn(missing_docs)]
+
+mod a11y;
+mod alias;
+mod chain;
+#[cfg(feature = "clap")]
+pub mod clap_support;
+mod command;
+/// The console window, its builder and the events it returns
 pub mod console;
+mod drop;
+mod find;
+#[cfg(feature = "log")]
+pub mod logger;
+mod notify;
+mod paste;
+#[cfg(all(feature = "process", not(target_arch = "wasm32")))]
+mod process;
+mod prompt;
+mod redirect;
+mod scroll;
+mod search;
+mod sink;
 mod tab;
+#[cfg(feature = "tracing")]
+pub mod tracing_layer;
+mod vars;
 pub use crate::console::ConsoleBuilder;
 pub use crate::console::ConsoleEvent;
+pub use crate::console::ConsoleOutput;
 pub use crate::console::ConsoleWindow;
+pub use crate::drop::DropBehavior;
+#[cfg(all(feature = "process", not(target_arch = "wasm32")))]
+pub use crate::process::ProcessHandle;
+pub use crate::search::{CaseMode, MatchMode};
+pub use crate::sink::CommandSession;
+pub use crate::sink::ConsoleSink;
+#[cfg(feature = "tracing")]
+pub use crate::tracing_layer::{ConsoleLayer, FilterHandle};
@@ -0,0 +1,147 @@
+//! A [`log`] backend that writes into a console
+//!
+//! ```ignore
+//! egui_console::logger::init(console.sink(), log::LevelFilter::Info).unwrap();
+//! log::warn!("this shows up in the console");
+//! ```
+//!
+//! Log lines are written above the prompt so they never get mixed up with what the user
+//! is typing. Records can come from any thread.
+use crate::ConsoleSink;
+use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+/// A [`log::Log`] implementation that writes to a [`ConsoleSink`]
+#[derive(Debug)]
+pub struct ConsoleLogger {
+    sink: ConsoleSink,
+    module_path: bool,
+    timestamp: bool,
+}
+
+impl ConsoleLogger {
+    /// Create a logger writing to the sink
+    /// # Arguments
+    /// * `sink` - where the log lines go, see [`crate::ConsoleWindow::sink`]
+    ///
+    pub fn new(sink: ConsoleSink) -> Self {
+        Self {
+            sink,
+            module_path: false,
+            timestamp: false,
+        }
+    }
+    /// Prefix each line with the module that logged it
+    pub fn module_path(mut self, module_path: bool) -> Self {
+        self.module_path = module_path;
+        self
+    }
+    /// Prefix each line with the UTC time of day, ignored on the web
+    pub fn timestamp(mut self, timestamp: bool) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    fn format(&self, record: &Record<'_>) -> String {
+        let mut line = String::new();
+        if self.timestamp {
+            if let Some(time) = time_of_day() {
+                line.push_str(&time);
+                line.push(' ');
+            }
+        }
+        line.push_str(&format!("[{}]", record.level()));
+        if self.module_path {
+            if let Some(path) = record.module_path() {
+                line.push_str(&format!(" {}", path));
+            }
+        }
+        line.push_str(&format!(" {}", record.args()));
+        line
+    }
+}
+
+impl Log for ConsoleLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if self.enabled(record.metadata()) {
+            self.sink.above_prompt(&self.format(record));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Install a logger that writes to the console
+/// # Arguments
+/// * `sink` - where the log lines go, see [`crate::ConsoleWindow::sink`]
+/// * `level` - the most verbose level to show
+///
+/// # Returns
+/// * an error if a logger is already installed
+///
+pub fn init(sink: ConsoleSink, level: LevelFilter) -> Result<(), SetLoggerError> {
+    init_with(ConsoleLogger::new(sink), level)
+}
+
+/// Install a configured [`ConsoleLogger`]
+/// # Arguments
+/// * `logger` - the logger
+/// * `level` - the most verbose level to show
+///
+/// # Returns
+/// * an error if a logger is already installed
+///
+pub fn init_with(logger: ConsoleLogger, level: LevelFilter) -> Result<(), SetLoggerError> {
+    log::set_boxed_logger(Box::new(logger))?;
+    set_level(level);
+    Ok(())
+}
+
+/// Change the most verbose level that gets logged
+///
+/// Handy for a `log_level` console command.
+///
+pub fn set_level(level: LevelFilter) {
+    log::set_max_level(level);
+}
+
+fn time_of_day() -> Option<String> {
+    let secs = web_time::SystemTime::now()
+        .duration_since(web_time::UNIX_EPOCH)
+        .ok()?
+        .as_secs()
+        % 86400;
+    Some(format!(
+        "{:02}:{:02}:{:02}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    ))
+}
+
+#[test]
+fn test_format() {
+    let console = crate::ConsoleBuilder::new().build();
+    let logger = ConsoleLogger::new(console.sink()).module_path(true);
+    let line = logger.format(
+        &Record::builder()
+            .level(log::Level::Warn)
+            .module_path(Some("app::net"))
+            .args(format_args!("lost {} packets", 3))
+            .build(),
+    );
+    assert_eq!(line, "[WARN] app::net lost 3 packets");
+
+    let logger = ConsoleLogger::new(console.sink()).timestamp(true);
+    let line = logger.format(
+        &Record::builder()
+            .level(log::Level::Info)
+            .args(format_args!("hi"))
+            .build(),
+    );
+    assert!(line.ends_with(" [INFO] hi"));
+    assert_eq!(line.find(' '), Some(8));
+}
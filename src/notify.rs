@@ -0,0 +1,96 @@
+use crate::ConsoleWindow;
+use egui::{Color32, Rect, Ui};
+use std::time::Duration;
+
+// how many notifications can be on screen at once
+const MAX_NOTIFICATIONS: usize = 4;
+
+/// A transient message shown over the console
+#[derive(Debug)]
+pub(crate) struct Notification {
+    text: String,
+    color: Color32,
+    duration: Duration,
+    // egui time at which it disappears, set the first time it is drawn
+    expires: Option<f64>,
+}
+
+impl ConsoleWindow {
+    /// Flash a short message over the console without writing it to the scrollback
+    /// # Arguments
+    /// * `text` - the message
+    /// * `color` - the text color
+    /// * `duration` - how long the message stays visible
+    ///
+    /// Up to four messages are stacked in the top right corner of the console, a
+    /// newer message pushes out the oldest one.
+    ///
+    pub fn notify(&mut self, text: &str, color: Color32, duration: Duration) {
+        if self.notifications.len() >= MAX_NOTIFICATIONS {
+            self.notifications.pop_front();
+        }
+        self.notifications.push_back(Notification {
+            text: text.to_string(),
+            color,
+            duration,
+            expires: None,
+        });
+    }
+
+    // draw the live notifications over the console rect and drop the expired ones
+    pub(crate) fn notify_ui(&mut self, ui: &Ui, rect: Rect) {
+        if self.notifications.is_empty() {
+            return;
+        }
+        let now = ui.input(|inp| inp.time);
+        for note in self.notifications.iter_mut() {
+            note.expires
+                .get_or_insert(now + note.duration.as_secs_f64());
+        }
+        self.notifications
+            .retain(|note| note.expires.is_some_and(|expires| expires > now));
+
+        let painter = ui.painter().with_clip_rect(rect);
+        let font = egui::TextStyle::Monospace.resolve(ui.style());
+        let fill = ui.visuals().window_fill;
+        let stroke = ui.visuals().window_stroke;
+        let margin = 4.0;
+        let mut top = rect.top() + margin;
+        let mut next_expiry = f64::INFINITY;
+        for note in &self.notifications {
+            let galley = painter.layout_no_wrap(note.text.clone(), font.clone(), note.color);
+            let size = galley.size() + egui::vec2(2.0 * margin, 2.0 * margin);
+            let note_rect =
+                Rect::from_min_size(egui::pos2(rect.right() - margin - size.x, top), size);
+            painter.rect(note_rect, 2.0, fill, stroke, egui::StrokeKind::Inside);
+            painter.galley(
+                note_rect.min + egui::vec2(margin, margin),
+                galley,
+                note.color,
+            );
+            top += size.y + margin;
+            if let Some(expires) = note.expires {
+                next_expiry = next_expiry.min(expires);
+            }
+        }
+        // make sure we get a frame to remove the next one even if nothing else happens
+        if next_expiry.is_finite() {
+            ui.ctx()
+                .request_repaint_after(Duration::from_secs_f64((next_expiry - now).max(0.0)));
+        }
+    }
+}
+
+#[test]
+fn test_notify_limit() {
+    let mut console = crate::ConsoleBuilder::new().build();
+    for i in 0..6 {
+        console.notify(
+            &format!("note {}", i),
+            Color32::WHITE,
+            Duration::from_secs(1),
+        );
+    }
+    assert_eq!(console.notifications.len(), MAX_NOTIFICATIONS);
+    assert_eq!(console.notifications[0].text, "note 2");
+}
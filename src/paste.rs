@@ -0,0 +1,191 @@
+use crate::ConsoleWindow;
+use egui::{Context, Event, Key, Rect, Ui};
+
+// pastes longer than this many chars ask first, see ConsoleBuilder::paste_limit
+pub(crate) const PASTE_LIMIT: usize = 10_000;
+
+#[cfg(feature = "persistence")]
+pub(crate) fn paste_limit_default() -> usize {
+    PASTE_LIMIT
+}
+
+impl ConsoleWindow {
+    // clean up this frame's pastes, a long one is held back until the user says yes
+    pub(crate) fn filter_pastes(&mut self, ctx: &Context) {
+        let filter = !self.keep_paste_controls;
+        let limit = self.paste_limit;
+        let mut held: Option<String> = None;
+        ctx.input_mut(|input| {
+            input.events.retain_mut(|event| {
+                let Event::Paste(text) = event else {
+                    return true;
+                };
+                if filter {
+                    *text = sanitize_paste(text);
+                }
+                if limit > 0 && text.chars().count() > limit {
+                    // two long pastes in one frame are asked about together
+                    held.get_or_insert_with(String::new).push_str(text);
+                    return false;
+                }
+                !text.is_empty()
+            });
+        });
+        if held.is_some() {
+            self.pending_paste = held;
+        }
+    }
+
+    // y pastes the held back text, anything else drops it, returns false if nothing is held
+    pub(crate) fn confirm_paste(&mut self, ctx: &Context, cursor: &mut usize) -> bool {
+        if self.pending_paste.is_none() {
+            return false;
+        }
+        let answer = ctx.input_mut(|input| {
+            let answer = input.events.iter().find_map(|event| match event {
+                Event::Key {
+                    key: Key::Y,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } => Some(!modifiers.command && !modifiers.alt),
+                Event::Text(text) => Some(text.eq_ignore_ascii_case("y")),
+                Event::Key { pressed: true, .. } | Event::Paste(_) => Some(false),
+                _ => None,
+            });
+            // the answer is not typed into the line
+            input.events.retain(|event| {
+                !matches!(event, Event::Text(_) | Event::Paste(_) | Event::Key { .. })
+            });
+            answer
+        });
+        match answer {
+            Some(true) => {
+                let text = self.pending_paste.take().unwrap_or_default();
+                let mut entered = false;
+                self.type_text(&text, cursor, &mut entered);
+            }
+            Some(false) => self.pending_paste = None,
+            None => {}
+        }
+        true
+    }
+
+    // the question above the input line while a paste is held back
+    pub(crate) fn paste_prompt_ui(&self, ui: &Ui, rect: Rect) {
+        if let Some(text) = &self.pending_paste {
+            let count = group_digits(text.chars().count());
+            let question = format!("Paste {} characters? [y/N]", count);
+            self.input_hint_ui(ui, rect, "paste_prompt", &question, None);
+        }
+    }
+}
+
+// 120000 becomes 120,000
+fn group_digits(n: usize) -> String {
+    let digits = n.to_string();
+    let groups: Vec<String> = digits
+        .as_bytes()
+        .rchunks(3)
+        .rev()
+        .map(|group| group.iter().map(|&b| b as char).collect())
+        .collect();
+    groups.join(",")
+}
+
+// line endings become \n, escape sequences and control characters other than \n and \t go
+pub(crate) fn sanitize_paste(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\r' => {
+                chars.next_if_eq(&'\n');
+                out.push('\n');
+            }
+            '\n' | '\t' => out.push(ch),
+            '\x1b' => match chars.next() {
+                // CSI, parameters up to a final byte
+                Some('[') => while chars.next().is_some_and(|c| !('@'..='~').contains(&c)) {},
+                // OSC, ended by BEL or ESC \
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            },
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[test]
+fn test_sanitize_paste() {
+    assert_eq!(sanitize_paste("a\r\nb\rc\n\td"), "a\nb\nc\n\td");
+    assert_eq!(sanitize_paste("bell\x07 nul\0 del\x7f"), "bell nul del");
+    assert_eq!(
+        sanitize_paste("\x1b[1;31mred\x1b[0m \x1b]0;title\x07ok \x1b]8;;x\x1b\\link"),
+        "red ok link"
+    );
+    assert_eq!(sanitize_paste("héllo 日本"), "héllo 日本");
+}
+
+#[test]
+fn test_long_paste() {
+    let ctx = Context::default();
+    let mut console = crate::ConsoleBuilder::new()
+        .paste_limit(5)
+        .inline_help(true)
+        .build();
+    console.register_command("ls", &[], "list files", |_, _| Ok(()));
+    let frame = |console: &mut ConsoleWindow, events: Vec<Event>| {
+        let _ = ctx.run(
+            egui::RawInput {
+                events,
+                ..Default::default()
+            },
+            |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ctx.memory_mut(|mem| mem.request_focus(console.id()));
+                    console.draw(ui);
+                });
+            },
+        );
+    };
+    frame(&mut console, vec![]);
+    frame(&mut console, vec![Event::Paste("ls\x07".to_string())]);
+    assert_eq!(console.text, ">> ls");
+
+    assert!(console.inline_help_text(None).is_some());
+    frame(&mut console, vec![Event::Paste(" -l -a".to_string())]);
+    assert_eq!(console.text, ">> ls");
+    assert!(console.pending_paste.is_some());
+    // the question is not covered by the help
+    assert!(console.inline_help_text(None).is_none());
+    // no is the default
+    frame(&mut console, vec![Event::Text("n".to_string())]);
+    assert!(console.pending_paste.is_none());
+    assert_eq!(console.text, ">> ls");
+
+    frame(&mut console, vec![Event::Paste(" -l -a".to_string())]);
+    frame(&mut console, vec![Event::Text("y".to_string())]);
+    assert_eq!(console.text, ">> ls -l -a");
+
+    // both long pastes of a frame are held
+    let pastes = vec![
+        Event::Paste(" | wc -w".to_string()),
+        Event::Paste(" -l -c".to_string()),
+    ];
+    frame(&mut console, pastes);
+    assert_eq!(console.pending_paste.as_deref(), Some(" | wc -w -l -c"));
+
+    assert_eq!(group_digits(7), "7");
+    assert_eq!(group_digits(1000), "1,000");
+    assert_eq!(group_digits(120_000), "120,000");
+    assert_eq!(group_digits(1_234_567), "1,234,567");
+}
@@ -0,0 +1,235 @@
+//! Run an OS process and stream its output into the console
+//!
+//! ```ignore
+//! let mut cmd = std::process::Command::new("cargo");
+//! cmd.arg("build");
+//! match console.spawn_process(cmd) {
+//!     Ok(_) => {}
+//!     Err(err) => {
+//!         console.write(&err.to_string());
+//!         console.prompt();
+//!     }
+//! }
+//! ```
+use crate::{ConsoleSink, ConsoleWindow};
+use std::{
+    io::{BufRead, BufReader, Read},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+// how long output may still arrive after the process exits, a child it left running can
+// hold the pipes open for much longer
+const DRAIN_WAIT: Duration = Duration::from_millis(250);
+
+/// A process started by [`ConsoleWindow::spawn_process`]
+///
+/// The handle can be cloned and sent to other threads.
+///
+#[derive(Debug, Clone)]
+pub struct ProcessHandle {
+    child: Arc<Mutex<Child>>,
+}
+
+impl ProcessHandle {
+    /// Kill the process
+    /// # Returns
+    /// * an error if the process could not be killed, killing one that already exited is Ok
+    ///
+    pub fn kill(&self) -> std::io::Result<()> {
+        let mut child = self.child.lock().unwrap();
+        if child.try_wait()?.is_some() {
+            return Ok(());
+        }
+        child.kill()
+    }
+    /// Check whether the process has exited
+    /// # Returns
+    /// * `Some(status)` once the process has exited, `None` while it is running
+    ///
+    pub fn try_wait(&self) -> std::io::Result<Option<ExitStatus>> {
+        self.child.lock().unwrap().try_wait()
+    }
+    /// The OS process id
+    pub fn id(&self) -> u32 {
+        self.child.lock().unwrap().id()
+    }
+}
+
+impl ConsoleWindow {
+    /// Start an OS process and show its output
+    /// # Arguments
+    /// * `cmd` - the command to run, stdin is closed and stdout and stderr are captured
+    ///
+    /// # Returns
+    /// * `ProcessHandle` - the running process
+    /// * an error if the process could not be started, the console is left as it was
+    ///
+    /// Call this instead of [`ConsoleWindow::prompt`] after receiving a command. Output
+    /// lines are written as they arrive, stderr lines as errors. When the process exits
+    /// its exit status is written and the console reprompts. Ctrl-C kills the process
+    /// instead of returning [`crate::ConsoleEvent::Interrupt`].
+    ///
+    /// Not available on the web.
+    ///
+    pub fn spawn_process(&mut self, mut cmd: Command) -> std::io::Result<ProcessHandle> {
+        let mut child = cmd
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let handle = ProcessHandle {
+            child: Arc::new(Mutex::new(child)),
+        };
+        let session = self.begin_command();
+        self.process = Some(handle.clone());
+
+        let detached = Arc::new(AtomicBool::new(false));
+        let readers = [
+            stdout.map(|out| {
+                stream_lines(out, session.sink(), ConsoleSink::println, detached.clone())
+            }),
+            stderr.map(|err| {
+                stream_lines(
+                    err,
+                    session.sink(),
+                    ConsoleSink::write_error,
+                    detached.clone(),
+                )
+            }),
+        ];
+        let waiter = handle.clone();
+        thread::spawn(move || {
+            let status = loop {
+                match waiter.try_wait() {
+                    Ok(Some(status)) => break Ok(status),
+                    Ok(None) => thread::sleep(Duration::from_millis(20)),
+                    Err(err) => break Err(err),
+                }
+            };
+            // the pipes close when the process exits, unless it handed them to a child that
+            // is still running, then the session ends without the rest of its output
+            let deadline = Instant::now() + DRAIN_WAIT;
+            let readers: Vec<_> = readers.into_iter().flatten().collect();
+            while !readers.iter().all(|reader| reader.is_finished()) && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(5));
+            }
+            detached.store(true, Ordering::Relaxed);
+            match status {
+                Ok(status) if status.success() => {
+                    session.sink().println(&format!("[{}]", status));
+                    session.finish(Ok(()));
+                }
+                Ok(status) => session.finish(Err(format!("[{}]", status))),
+                Err(err) => session.finish(Err(err.to_string())),
+            }
+        });
+        Ok(handle)
+    }
+
+    // ctrl-c while a process is running, false if there is none
+    pub(crate) fn kill_process(&self) -> bool {
+        match &self.process {
+            Some(process) => {
+                let _ = process.kill();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// copy lines from a pipe to the sink until the pipe closes or the session has ended
+fn stream_lines(
+    pipe: impl Read + Send + 'static,
+    sink: ConsoleSink,
+    write: fn(&ConsoleSink, &str),
+    detached: Arc<AtomicBool>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(pipe);
+        let mut line = Vec::new();
+        // not lines() so that output that isn't utf8 doesn't end the stream
+        while let Ok(n) = reader.read_until(b'\n', &mut line) {
+            if n == 0 || detached.load(Ordering::Relaxed) {
+                break;
+            }
+            let text = String::from_utf8_lossy(&line);
+            write(&sink, text.trim_end_matches(['\n', '\r']));
+            line.clear();
+        }
+    })
+}
+
+#[cfg(unix)]
+#[test]
+fn test_spawn_process() {
+    let ctx = egui::Context::default();
+    let mut console = crate::ConsoleBuilder::new().build();
+    let mut cmd = Command::new("sh");
+    cmd.args(["-c", "echo out; echo err >&2; exit 3"]);
+    let handle = console.spawn_process(cmd).unwrap();
+    assert!(console.is_busy());
+
+    for _ in 0..500 {
+        console.drain_sink(&ctx);
+        if !console.is_busy() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(!console.is_busy());
+    assert_eq!(handle.try_wait().unwrap().unwrap().code(), Some(3));
+    assert!(console.text.contains("out\n"));
+    assert!(console.text.contains("err\n"));
+    assert!(console.text.ends_with("[exit status: 3]\n>> "));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_process_leaves_child_running() {
+    let ctx = egui::Context::default();
+    let mut console = crate::ConsoleBuilder::new().build();
+    let mut cmd = Command::new("sh");
+    // the sleep keeps stdout and stderr open after sh exits
+    cmd.args(["-c", "echo started; sleep 30 &"]);
+    console.spawn_process(cmd).unwrap();
+
+    let start = Instant::now();
+    while console.is_busy() && start.elapsed() < Duration::from_secs(5) {
+        console.drain_sink(&ctx);
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(!console.is_busy());
+    assert!(console.text.contains("started\n"));
+    assert!(console.text.ends_with("[exit status: 0]\n>> "));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_kill_process() {
+    let ctx = egui::Context::default();
+    let mut console = crate::ConsoleBuilder::new().build();
+    let mut cmd = Command::new("sleep");
+    cmd.arg("30");
+    console.spawn_process(cmd).unwrap();
+    console.kill_process();
+
+    for _ in 0..500 {
+        console.drain_sink(&ctx);
+        if !console.is_busy() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(!console.is_busy());
+    assert!(console.process.is_none());
+    assert!(console.text.contains("[signal: 9"));
+}
@@ -0,0 +1,146 @@
+use crate::ConsoleWindow;
+
+type Resolver = Box<dyn Fn(Option<&str>) -> String>;
+
+/// A placeholder added with [`ConsoleWindow::set_prompt_placeholder`]
+pub(crate) struct PromptPlaceholder {
+    name: String,
+    resolver: Resolver,
+}
+
+impl std::fmt::Debug for PromptPlaceholder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PromptPlaceholder")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ConsoleWindow {
+    /// Add a placeholder for the prompt template
+    /// # Arguments
+    /// * `name` - used as `{name}` or `{name:arg}` in the template
+    /// * `resolver` - called with the `arg`, if there is one, each time the prompt is drawn
+    ///
+    /// A placeholder with the name of a built in one replaces it.
+    ///
+    pub fn set_prompt_placeholder(
+        &mut self,
+        name: &str,
+        resolver: impl Fn(Option<&str>) -> String + 'static,
+    ) {
+        self.prompt_placeholders.retain(|p| p.name != name);
+        self.prompt_placeholders.push(PromptPlaceholder {
+            name: name.to_string(),
+            resolver: Box::new(resolver),
+        });
+    }
+
+    // turn the template into the prompt for the next input line
+    pub(crate) fn render_prompt(&mut self) {
+        let Some(template) = &self.prompt_template else {
+            return;
+        };
+        let mut prompt = String::new();
+        let mut rest = template.as_str();
+        while let Some(start) = rest.find('{') {
+            prompt.push_str(&rest[..start]);
+            rest = &rest[start + 1..];
+            if let Some(after) = rest.strip_prefix('{') {
+                prompt.push('{');
+                rest = after;
+                continue;
+            }
+            let Some(end) = rest.find('}') else {
+                prompt.push('{');
+                continue;
+            };
+            let placeholder = &rest[..end];
+            match self.resolve_placeholder(placeholder) {
+                Some(value) => prompt.push_str(&value),
+                None => {
+                    prompt.push('{');
+                    prompt.push_str(placeholder);
+                    prompt.push('}');
+                }
+            }
+            rest = &rest[end + 1..];
+        }
+        prompt.push_str(rest);
+        self.prompt_len = prompt.chars().count();
+        self.prompt = prompt;
+    }
+
+    fn resolve_placeholder(&self, placeholder: &str) -> Option<String> {
+        let (name, arg) = match placeholder.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (placeholder, None),
+        };
+        if let Some(custom) = self.prompt_placeholders.iter().find(|p| p.name == name) {
+            return Some((custom.resolver)(arg));
+        }
+        let value = match (name, arg) {
+            ("cwd", None) => current_dir(false),
+            ("cwd_short", None) => current_dir(true),
+            ("time", arg) => time_of_day(arg.unwrap_or("%H:%M:%S")),
+            ("status", None) => match self.last_status {
+                None => "✓".to_string(),
+                Some(_) => "✗".to_string(),
+            },
+            ("var", Some(var)) => self.get_var(var).unwrap_or("").to_string(),
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+fn current_dir(short: bool) -> String {
+    let Ok(dir) = std::env::current_dir() else {
+        return String::new();
+    };
+    match dir.file_name() {
+        Some(name) if short => name.to_string_lossy().to_string(),
+        _ => dir.display().to_string(),
+    }
+}
+
+// %H %M and %S of the local time, on the web there is no timezone database so it is UTC
+fn time_of_day(format: &str) -> String {
+    #[cfg(not(target_arch = "wasm32"))]
+    let secs = {
+        use chrono::Timelike;
+        chrono::Local::now().num_seconds_from_midnight() as u64
+    };
+    #[cfg(target_arch = "wasm32")]
+    let secs = web_time::SystemTime::now()
+        .duration_since(web_time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() % 86400);
+    format
+        .replace("%H", &format!("{:02}", secs / 3600))
+        .replace("%M", &format!("{:02}", secs / 60 % 60))
+        .replace("%S", &format!("{:02}", secs % 60))
+}
+
+#[test]
+fn test_render_prompt() {
+    let mut console = crate::ConsoleBuilder::new()
+        .prompt_template("{status} {var:USER}@{host} {{{time:%H}}} {nope}> ")
+        .build();
+    console.set_var("USER", "ann");
+    console.set_prompt_placeholder("host", |_| "box".to_string());
+    console.render_prompt();
+    assert!(console.prompt.starts_with("✓ ann@box {"));
+    assert!(console.prompt.ends_with("} {nope}> "));
+    assert_eq!(console.prompt_len, console.prompt.chars().count());
+
+    console.set_last_status(Err(1));
+    console.prompt();
+    assert!(console.text.ends_with(&console.prompt));
+    assert!(console.prompt.starts_with("✗ "));
+
+    // the local hour, checked on both sides in case the hour turns
+    let hour = || chrono::Local::now().format("%H").to_string();
+    let before = hour();
+    let shown = time_of_day("%H");
+    assert!(shown == before || shown == hour());
+}
@@ -0,0 +1,127 @@
+use crate::command::split_args;
+use crate::ConsoleWindow;
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+};
+
+/// Output of the running command going to a file
+#[derive(Debug)]
+pub(crate) struct Capture {
+    path: String,
+    file: BufWriter<File>,
+}
+
+impl ConsoleWindow {
+    // strip a `> path` or `>> path` from the line and start capturing into the file
+    pub(crate) fn start_redirect(&mut self, line: String) -> Result<String, String> {
+        if self.redirect_disabled {
+            return Ok(line);
+        }
+        let Some((command, path, append)) = split_redirect(&line) else {
+            return Ok(line);
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(&path)
+            .map_err(|err| format!("{}: {}", path, err))?;
+        self.capture = Some(Capture {
+            path,
+            file: BufWriter::new(file),
+        });
+        Ok(command)
+    }
+
+    // the command is done, close the file
+    pub(crate) fn end_redirect(&mut self) {
+        if let Some(mut capture) = self.capture.take() {
+            if let Err(err) = capture.file.flush() {
+                self.write_error_display(&format!("{}: {}", capture.path, err));
+            }
+        }
+    }
+
+    // write to the capture file if there is one, true if the text was taken
+    pub(crate) fn capture_text(&mut self, text: &str) -> bool {
+        let Some(capture) = self.capture.as_mut() else {
+            return false;
+        };
+        if let Err(err) = capture.file.write_all(text.as_bytes()) {
+            let msg = format!("{}: {}", capture.path, err);
+            self.capture = None;
+            self.write_error_display(&msg);
+        }
+        true
+    }
+}
+
+// find an unquoted > or >> followed by a single word
+fn split_redirect(line: &str) -> Option<(String, String, bool)> {
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let pos = line.char_indices().find_map(|(i, ch)| {
+        if escaped {
+            escaped = false;
+            return None;
+        }
+        match (quote, ch) {
+            (Some('"'), '\\') => escaped = true,
+            (Some(qc), ch) if ch == qc => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(ch),
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+        None
+    })?;
+    let (target, append) = match line[pos + 1..].strip_prefix('>') {
+        Some(rest) => (rest, true),
+        None => (&line[pos + 1..], false),
+    };
+    match split_args(target).ok()?.as_slice() {
+        [path] => Some((line[..pos].trim_end().to_string(), path.clone(), append)),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_split_redirect() {
+    assert_eq!(
+        split_redirect("dir > files.txt"),
+        Some(("dir".to_string(), "files.txt".to_string(), false))
+    );
+    assert_eq!(
+        split_redirect("dir -l>>'my files.txt'"),
+        Some(("dir -l".to_string(), "my files.txt".to_string(), true))
+    );
+    assert_eq!(split_redirect("echo '>' x"), None);
+    assert_eq!(split_redirect("echo \"a\\\">\" x"), None);
+    assert_eq!(split_redirect("dir >"), None);
+    assert_eq!(split_redirect("a > b c"), None);
+}
+
+#[test]
+fn test_redirect() {
+    let path = std::env::temp_dir().join(format!("egui_console_{}.txt", std::process::id()));
+    let mut console = crate::ConsoleBuilder::new().build();
+    console.register_command("list", &[], "", |console, _| {
+        console.write("one");
+        console.write("two");
+        Ok(())
+    });
+    console.run_registered(format!("list > '{}'", path.display()));
+    console.run_registered(format!("list >> '{}'", path.display()));
+    assert!(!console.text.contains("one"));
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "one\ntwo\none\ntwo\n"
+    );
+    std::fs::remove_file(&path).unwrap();
+
+    console.run_registered("list > /no/such/dir/x".to_string());
+    assert!(console.text.contains("/no/such/dir/x: "));
+    assert!(!console.text.contains("one"));
+}
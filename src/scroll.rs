@@ -0,0 +1,137 @@
+use crate::ConsoleWindow;
+use egui::{Key, Modifiers, Ui};
+
+/// A move of the view asked for since the last frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ScrollRequest {
+    Top,
+    Bottom,
+    Lines(i32),
+    Pages(i32),
+}
+
+/// Where the view was on the last frame
+#[derive(Debug, Default)]
+pub(crate) struct ScrollState {
+    offset: f32,
+    page: f32,
+    content: f32,
+}
+
+impl ConsoleWindow {
+    /// Scroll the view to the first line
+    ///
+    /// Takes effect the next time the console is drawn, the input cursor does not move.
+    ///
+    pub fn scroll_to_top(&mut self) {
+        self.scroll_request = Some(ScrollRequest::Top);
+    }
+
+    /// Scroll the view to the last line
+    ///
+    /// Takes effect the next time the console is drawn, the input cursor does not move.
+    ///
+    pub fn scroll_to_bottom(&mut self) {
+        self.scroll_request = Some(ScrollRequest::Bottom);
+    }
+
+    /// Scroll the view by a number of lines
+    /// # Arguments
+    /// * `lines` - negative scrolls up, positive down
+    ///
+    /// Takes effect the next time the console is drawn, the input cursor does not move.
+    ///
+    pub fn scroll_lines(&mut self, lines: i32) {
+        self.scroll_request = Some(ScrollRequest::Lines(lines));
+    }
+
+    // page up and down, with or without shift, and ctrl-home / ctrl-end move the view
+    pub(crate) fn scroll_key(&mut self, modifiers: Modifiers, key: Key) -> bool {
+        if modifiers.alt {
+            return false;
+        }
+        let request = match key {
+            Key::PageUp if !modifiers.ctrl => ScrollRequest::Pages(-1),
+            Key::PageDown if !modifiers.ctrl => ScrollRequest::Pages(1),
+            Key::Home if modifiers.ctrl && !modifiers.shift => ScrollRequest::Top,
+            Key::End if modifiers.ctrl && !modifiers.shift => ScrollRequest::Bottom,
+            _ => return false,
+        };
+        self.scroll_request = Some(request);
+        true
+    }
+
+    // the offset the scroll area should jump to this frame, if one was asked for
+    pub(crate) fn take_scroll_offset(&mut self, ui: &Ui) -> Option<f32> {
+        let row = ui.text_style_height(&egui::TextStyle::Monospace);
+        let state = &self.scroll_state;
+        let offset = match self.scroll_request.take()? {
+            ScrollRequest::Top => 0.0,
+            ScrollRequest::Bottom => state.content,
+            ScrollRequest::Lines(lines) => state.offset + lines as f32 * row,
+            // keep a line of the old page in view
+            ScrollRequest::Pages(pages) => {
+                state.offset + pages as f32 * (state.page - row).max(row)
+            }
+        };
+        // the text may have grown since, the scroll area stops at its end anyway
+        Some(offset.min(state.content - state.page).max(0.0))
+    }
+
+    // remember where the view ended up
+    pub(crate) fn store_scroll(&mut self, offset: f32, page: f32, content: f32) {
+        self.scroll_state = ScrollState {
+            offset,
+            page,
+            content,
+        };
+    }
+}
+
+#[test]
+fn test_scroll() {
+    let ctx = egui::Context::default();
+    ctx.style_mut(|style| style.scroll_animation = egui::style::ScrollAnimation::none());
+    let mut console = crate::ConsoleBuilder::new().build();
+    for i in 0..200 {
+        console.write(&format!("line {}", i));
+    }
+    let frame = |console: &mut crate::ConsoleWindow, events: Vec<egui::Event>| {
+        let input = egui::RawInput {
+            events,
+            screen_rect: Some(egui::Rect::from_min_size(
+                egui::Pos2::ZERO,
+                egui::vec2(400.0, 300.0),
+            )),
+            ..Default::default()
+        };
+        let mut bottom = false;
+        let _ = ctx.run(input, |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ctx.memory_mut(|mem| mem.request_focus(console.id()));
+                bottom = console.draw_full(ui).scrolled_to_bottom;
+            });
+        });
+        bottom
+    };
+    let page_up = crate::console::key(Key::PageUp);
+    frame(&mut console, vec![]);
+    assert!(frame(&mut console, vec![]));
+
+    frame(&mut console, vec![page_up.clone()]);
+    assert!(!frame(&mut console, vec![]));
+    let after_page = console.scroll_state.offset;
+    assert!(console.scroll_state.page > 0.0);
+
+    console.scroll_lines(-2);
+    frame(&mut console, vec![]);
+    assert!(console.scroll_state.offset < after_page);
+
+    console.scroll_to_top();
+    frame(&mut console, vec![]);
+    assert_eq!(console.scroll_state.offset, 0.0);
+
+    console.scroll_to_bottom();
+    frame(&mut console, vec![]);
+    assert!(frame(&mut console, vec![]));
+}
@@ -0,0 +1,469 @@
+use crate::find::{find_matches, highlight_layout};
+use crate::ConsoleWindow;
+use egui::{
+    text::CCursor, text::CCursorRange, Align2, Context, Key, KeyboardShortcut, Modifiers, Rect,
+    TextEdit, Ui,
+};
+use std::collections::{HashSet, VecDeque};
+use std::ops::Range;
+
+// how many matches the popup lists
+const POPUP_MATCHES: usize = 8;
+
+/// The keys that open the last accepted search again, alt-n and alt-p by default
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RecallKeys {
+    pub(crate) next: KeyboardShortcut,
+    pub(crate) previous: KeyboardShortcut,
+}
+
+impl Default for RecallKeys {
+    fn default() -> Self {
+        Self {
+            next: KeyboardShortcut::new(Modifiers::ALT, Key::N),
+            previous: KeyboardShortcut::new(Modifiers::ALT, Key::P),
+        }
+    }
+}
+
+/// A search that put its match on the input line
+#[derive(Debug)]
+pub(crate) struct LastSearch {
+    query: String,
+    index: usize,
+    // the accepted match, once the line is edited the search is forgotten
+    line: String,
+}
+
+/// How reverse-i-search matches the query against the history
+///
+/// Alt-r while searching switches to the next mode.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum MatchMode {
+    /// The query is a substring, prefix matches come first
+    Plain,
+    /// Like plain, then the query's characters in order with gaps, `gcm` finds `git commit -m`
+    #[default]
+    Fuzzy,
+    /// The query is a regular expression, the most recent match comes first
+    ///
+    /// Needs the regex feature, without it this matches like plain.
+    Regex,
+}
+
+impl MatchMode {
+    // the mode alt-r switches to
+    fn next(self) -> Self {
+        match self {
+            MatchMode::Plain => MatchMode::Fuzzy,
+            MatchMode::Fuzzy if cfg!(feature = "regex") => MatchMode::Regex,
+            _ => MatchMode::Plain,
+        }
+    }
+}
+
+/// How reverse-i-search treats upper and lower case
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum CaseMode {
+    /// Case has to match
+    Sensitive,
+    /// Case is ignored
+    Insensitive,
+    /// Case is ignored unless the query has an upper case letter
+    #[default]
+    Smart,
+}
+
+impl CaseMode {
+    pub(crate) fn ignore_case(self, query: &str) -> bool {
+        match self {
+            CaseMode::Sensitive => false,
+            CaseMode::Insensitive => true,
+            CaseMode::Smart => !query.chars().any(char::is_uppercase),
+        }
+    }
+}
+
+// a query ready to be matched against the history, the flag is true to ignore case
+enum Matcher<'a> {
+    Plain(&'a str, bool),
+    Fuzzy(&'a str, bool),
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+    // a regex that did not compile, it matches nothing
+    #[cfg_attr(not(feature = "regex"), allow(dead_code))]
+    Invalid,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(mode: MatchMode, case: CaseMode, query: &'a str) -> Self {
+        let ignore_case = case.ignore_case(query);
+        match mode {
+            MatchMode::Fuzzy => Matcher::Fuzzy(query, ignore_case),
+            #[cfg(feature = "regex")]
+            MatchMode::Regex => match regex::RegexBuilder::new(query)
+                .case_insensitive(ignore_case)
+                .build()
+            {
+                Ok(re) => Matcher::Regex(re),
+                Err(_) => Matcher::Invalid,
+            },
+            _ => Matcher::Plain(query, ignore_case),
+        }
+    }
+
+    // how well the command matches and the byte ranges that matched, None if it doesn't
+    //
+    // a prefix beats a substring, which beats the query's characters appearing in order
+    // with gaps. Fewer skipped characters is better.
+    fn find(&self, command: &str) -> Option<(u32, Vec<Range<usize>>)> {
+        let (query, ignore_case) = match self {
+            Matcher::Plain(query, ignore_case) | Matcher::Fuzzy(query, ignore_case) => {
+                (*query, *ignore_case)
+            }
+            #[cfg(feature = "regex")]
+            Matcher::Regex(re) => {
+                // all regex matches rank the same, so the most recent comes first
+                let found = re.find(command).filter(|m| !m.is_empty())?;
+                return Some((1, vec![found.range()]));
+            }
+            Matcher::Invalid => return None,
+        };
+        if query.is_empty() {
+            return None;
+        }
+        if let Some(matched) = find_matches(command, query, !ignore_case)
+            .into_iter()
+            .next()
+        {
+            let score = if matched.start == 0 { 3000 } else { 2000 };
+            return Some((score, vec![matched]));
+        }
+        if !matches!(self, Matcher::Fuzzy(..)) {
+            return None;
+        }
+        let mut chars = command.char_indices();
+        let mut ranges = Vec::new();
+        let mut gaps = 0u32;
+        for want in query.chars() {
+            loop {
+                let (i, ch) = chars.next()?;
+                let same = if ignore_case {
+                    ch.to_lowercase().eq(want.to_lowercase())
+                } else {
+                    ch == want
+                };
+                if same {
+                    ranges.push(i..i + ch.len_utf8());
+                    break;
+                }
+                if !ranges.is_empty() {
+                    gaps += 1;
+                }
+            }
+        }
+        Some((1000u32.saturating_sub(gaps).max(1), ranges))
+    }
+}
+
+impl ConsoleWindow {
+    // the query changed, rank the history again and show the best match
+    pub(crate) fn update_search(&mut self, query: String) {
+        let matcher = Matcher::new(self.search_mode, self.search_case, &query);
+        self.search_invalid = matches!(matcher, Matcher::Invalid);
+        self.search_matches = rank_history(&self.command_history, &matcher);
+        self.search_index = 0;
+        self.search_partial = Some(query);
+        self.redraw_search();
+    }
+
+    // the user edited the query, search again and keep the cursor where it was in it
+    pub(crate) fn search_typed(&mut self, ctx: &Context) {
+        let state = TextEdit::load_state(ctx, self.id());
+        let (_, end) = self.search_bounds();
+        let from_end = state
+            .as_ref()
+            .and_then(|state| state.cursor.char_range())
+            .map_or(0, |range| end.saturating_sub(range.primary.index));
+        let query = self.get_search_text();
+        self.update_search(query);
+
+        // the position in the prompt may have changed its length
+        let (start, end) = self.search_bounds();
+        if let Some(mut state) = state {
+            let cursor = CCursor::new(end.saturating_sub(from_end).max(start));
+            state.cursor.set_char_range(Some(CCursorRange::one(cursor)));
+            state.store(ctx, self.id());
+            self.force_cursor_to_end = false;
+        }
+    }
+
+    // alt-r switches to the next match mode and searches again
+    pub(crate) fn next_search_mode(&mut self) {
+        self.search_mode = self.search_mode.next();
+        let query = self.search_partial.clone().unwrap_or_default();
+        self.update_search(query);
+    }
+
+    // ctrl-r again or down steps to the next ranked match
+    pub(crate) fn next_search_match(&mut self) {
+        if self.search_index + 1 < self.search_matches.len() {
+            self.search_index += 1;
+            self.redraw_search();
+        }
+    }
+
+    // up steps back to the previous ranked match
+    pub(crate) fn previous_search_match(&mut self) {
+        if self.search_index > 0 {
+            self.search_index -= 1;
+            self.redraw_search();
+        }
+    }
+
+    // leave search mode with the match on the input line, ready to edit or enter
+    pub(crate) fn accept_search(&mut self) {
+        let line = self.shown_match().to_string();
+        let history_cursor = self.history_cursor;
+        self.last_search = match &self.search_partial {
+            Some(query) if !line.is_empty() => Some(LastSearch {
+                query: query.clone(),
+                index: self.search_index,
+                line: line.clone(),
+            }),
+            _ => None,
+        };
+        self.exit_search_mode();
+        self.text.push_str(&line);
+        self.history_cursor = history_cursor;
+    }
+
+    // the step through the matches for a recall key, 1 for the next match and -1 for the previous
+    pub(crate) fn recall_step(&self, modifiers: Modifiers, key: Key) -> Option<isize> {
+        let is = |shortcut: KeyboardShortcut| {
+            shortcut.logical_key == key && modifiers.matches_exact(shortcut.modifiers)
+        };
+        if is(self.recall_keys.next) {
+            Some(1)
+        } else if is(self.recall_keys.previous) {
+            Some(-1)
+        } else {
+            None
+        }
+    }
+
+    // open the last accepted search again at the match step away from the accepted one,
+    // false if there is none or the line was changed since
+    pub(crate) fn recall_search(&mut self, step: isize, cursor: usize) -> bool {
+        let Some(last) = self.last_search.take() else {
+            return false;
+        };
+        if self.search_partial.is_some() || self.get_last_line() != last.line {
+            return false;
+        }
+        self.enter_search_mode(cursor);
+        self.update_search(last.query);
+        if let Some(last_match) = self.search_matches.len().checked_sub(1) {
+            self.search_index = last.index.saturating_add_signed(step).min(last_match);
+            self.redraw_search();
+        }
+        true
+    }
+
+    // list the best matches above the input line, the current one selected
+    pub(crate) fn search_popup_ui(&mut self, ui: &Ui, rect: Rect) {
+        let Some(query) = &self.search_partial else {
+            return;
+        };
+        if self.search_matches.is_empty() {
+            return;
+        }
+        // keep the selection in the window of matches that is listed
+        let first = self.search_index.saturating_sub(POPUP_MATCHES - 1);
+        let bottom = self.input_top.clamp(rect.top(), rect.bottom());
+        let matcher = Matcher::new(self.search_mode, self.search_case, query);
+        let mut clicked = None;
+        egui::Area::new(self.id().with("search_popup"))
+            .order(egui::Order::Foreground)
+            .pivot(Align2::LEFT_BOTTOM)
+            .fixed_pos(egui::pos2(rect.left(), bottom))
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    let shown = self.search_matches.iter().enumerate().skip(first);
+                    for (n, &index) in shown.take(POPUP_MATCHES) {
+                        let command = &self.command_history[index];
+                        let highlights: Vec<_> = matcher
+                            .find(command)
+                            .map(|(_, ranges)| ranges)
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|range| (range, false))
+                            .collect();
+                        let job = highlight_layout(ui, command, &highlights, f32::INFINITY);
+                        if ui.selectable_label(n == self.search_index, job).clicked() {
+                            clicked = Some(n);
+                        }
+                    }
+                });
+            });
+        if let Some(n) = clicked {
+            self.search_index = n;
+            self.accept_search();
+            ui.ctx().memory_mut(|mem| mem.request_focus(self.id()));
+        }
+    }
+
+    // escape or ctrl-g leave the search with the line as it was before it
+    pub(crate) fn abort_search(&mut self) {
+        let saved = self.search_saved_input.take();
+        self.exit_search_mode();
+        if let Some((input, at)) = saved {
+            self.text.push_str(&input);
+            self.restore_cursor = Some(self.last_line_char_offset() + self.prompt_len + at);
+        }
+    }
+
+    // `(reverse-i-search 2/7) query:`, the position is left out while nothing matches
+    pub(crate) fn search_prompt(&self) -> String {
+        format!(
+            "{}{}:",
+            self.search_prompt_head(),
+            self.search_partial.as_deref().unwrap_or("")
+        )
+    }
+
+    // the part of the search prompt before the query
+    fn search_prompt_head(&self) -> String {
+        let mode = match self.search_mode {
+            MatchMode::Plain => "plain ",
+            MatchMode::Regex => "regex ",
+            _ => "",
+        };
+        let position = match self.search_matches.len() {
+            0 => String::new(),
+            count => format!(" {}/{}", self.search_index + 1, count),
+        };
+        let invalid = if self.search_invalid {
+            "(invalid regex) "
+        } else {
+            ""
+        };
+        format!("({}reverse-i-search{}) {}", mode, position, invalid)
+    }
+
+    // the history entry showing after the search prompt
+    fn shown_match(&self) -> &str {
+        self.search_matches
+            .get(self.search_index)
+            .map_or("", |&index| self.command_history[index].as_str())
+    }
+
+    // char offsets in the text of the start and end of the query being typed
+    pub(crate) fn search_bounds(&self) -> (usize, usize) {
+        let line_start = self.last_line_char_offset();
+        let line_end = line_start + self.text[self.last_line_offset()..].chars().count();
+        let start = line_start + self.search_prompt_head().chars().count();
+        // the line ends with the ':' and the match
+        let tail = 1 + self.shown_match().chars().count();
+        (start, line_end.saturating_sub(tail).max(start))
+    }
+
+    // draw the search line again for the current query and match
+    fn redraw_search(&mut self) {
+        self.history_cursor = self.search_matches.get(self.search_index).copied();
+        self.text.truncate(self.last_line_offset());
+        self.prompt = self.search_prompt();
+        self.text.push_str(&self.prompt);
+        let line = self.shown_match().to_string();
+        self.text.push_str(&line);
+        self.force_cursor_to_end = true;
+    }
+}
+
+// the history entries that match, best first and each command once
+fn rank_history(history: &VecDeque<String>, matcher: &Matcher) -> Vec<usize> {
+    let mut scored: Vec<(u32, usize)> = history
+        .iter()
+        .enumerate()
+        .filter_map(|(i, command)| matcher.find(command).map(|(score, _)| (score, i)))
+        .collect();
+    // higher scores first, the most recent first among equals
+    scored.sort_unstable_by(|a, b| b.cmp(a));
+    let mut seen = HashSet::new();
+    scored
+        .into_iter()
+        .filter(|(_, i)| seen.insert(history[*i].as_str()))
+        .map(|(_, i)| i)
+        .collect()
+}
+
+#[test]
+fn test_rank_history() {
+    let history: VecDeque<String> = [
+        "git commit -m first",
+        "cargo build",
+        "git checkout main",
+        "echo gcm",
+        "git commit -m first",
+        "gcm",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    let rank = |mode, query| rank_history(&history, &Matcher::new(mode, CaseMode::Smart, query));
+    let ranked: Vec<&str> = rank(MatchMode::Fuzzy, "gcm")
+        .into_iter()
+        .map(|i| history[i].as_str())
+        .collect();
+    assert_eq!(
+        ranked,
+        vec![
+            "gcm",
+            "echo gcm",
+            "git commit -m first",
+            "git checkout main"
+        ]
+    );
+    // the duplicate is found at its most recent place, fuzzy matches come after
+    assert_eq!(rank(MatchMode::Fuzzy, "git com"), vec![4, 2]);
+    assert!(rank(MatchMode::Fuzzy, "").is_empty());
+    assert!(rank(MatchMode::Fuzzy, "xyz").is_empty());
+
+    assert_eq!(rank(MatchMode::Plain, "gcm"), vec![5, 3]);
+    assert_eq!(rank(MatchMode::Plain, "git com"), vec![4]);
+    #[cfg(feature = "regex")]
+    {
+        assert_eq!(rank(MatchMode::Regex, "^git (commit|checkout)"), vec![4, 2]);
+        assert_eq!(rank(MatchMode::Regex, "g.m$"), vec![5, 3]);
+        assert!(matches!(
+            Matcher::new(MatchMode::Regex, CaseMode::Smart, "git ("),
+            Matcher::Invalid
+        ));
+    }
+}
+
+#[test]
+fn test_case_mode() {
+    let history: VecDeque<String> = ["cargo build", "Cargo.toml"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let rank = |mode, case, query| rank_history(&history, &Matcher::new(mode, case, query));
+    assert_eq!(rank(MatchMode::Plain, CaseMode::Smart, "Cargo"), vec![1]);
+    assert_eq!(rank(MatchMode::Plain, CaseMode::Smart, "cargo"), vec![1, 0]);
+    assert_eq!(
+        rank(MatchMode::Plain, CaseMode::Insensitive, "Cargo"),
+        vec![1, 0]
+    );
+    assert!(rank(MatchMode::Plain, CaseMode::Sensitive, "CARGO").is_empty());
+    assert_eq!(rank(MatchMode::Fuzzy, CaseMode::Smart, "ctml"), vec![1]);
+    assert!(rank(MatchMode::Fuzzy, CaseMode::Smart, "CB").is_empty());
+    assert_eq!(rank(MatchMode::Fuzzy, CaseMode::Insensitive, "CB"), vec![0]);
+    #[cfg(feature = "regex")]
+    assert_eq!(rank(MatchMode::Regex, CaseMode::Smart, "^c"), vec![1, 0]);
+}
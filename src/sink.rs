@@ -0,0 +1,384 @@
+use crate::ConsoleWindow;
+use egui::{Context, Event, Rect, Ui};
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// Output sent to the console from a [`ConsoleSink`]
+#[derive(Debug)]
+pub(crate) enum SinkMessage {
+    Print(String),
+    Println(String),
+    Error(String),
+    AbovePrompt(String),
+    Finished(u64, Result<(), String>),
+}
+
+/// The queue shared between a console and all of its sinks
+#[derive(Debug, Default)]
+pub(crate) struct SinkQueue {
+    messages: Mutex<VecDeque<SinkMessage>>,
+    // so that writers can wake up the ui, known after the first draw
+    ctx: Mutex<Option<Context>>,
+    // 0 means unbounded
+    limit: AtomicUsize,
+    dropped: AtomicUsize,
+    // dropped since the console last looked
+    dropped_unreported: AtomicUsize,
+    next_session: AtomicU64,
+}
+
+impl SinkQueue {
+    pub(crate) fn set_limit(&self, limit: usize) {
+        self.limit.store(limit, Ordering::Relaxed);
+    }
+
+    fn push(&self, msg: SinkMessage) {
+        {
+            let mut messages = self.messages.lock().unwrap();
+            let limit = self.limit.load(Ordering::Relaxed);
+            if limit > 0 && messages.len() >= limit {
+                // drop the oldest output, but never the end of a session
+                if let Some(pos) = messages
+                    .iter()
+                    .position(|m| !matches!(m, SinkMessage::Finished(..)))
+                {
+                    messages.remove(pos);
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    self.dropped_unreported.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            messages.push_back(msg);
+        }
+        if let Some(ctx) = self.ctx.lock().unwrap().as_ref() {
+            ctx.request_repaint();
+        }
+    }
+}
+
+/// A handle for writing to a console from any thread
+///
+/// Get one from [`ConsoleWindow::sink`] or [`CommandSession::sink`]. Output is queued and
+/// written the next time the console is drawn, a repaint is requested so that happens
+/// promptly. Output from one sink always arrives in the order it was written.
+///
+#[derive(Debug, Clone)]
+pub struct ConsoleSink {
+    queue: Arc<SinkQueue>,
+}
+
+impl ConsoleSink {
+    /// Append text to the current line
+    pub fn print(&self, text: &str) {
+        self.queue.push(SinkMessage::Print(text.to_string()));
+    }
+    /// Write text on a new line
+    pub fn println(&self, text: &str) {
+        self.queue.push(SinkMessage::Println(text.to_string()));
+    }
+    /// Write an error message on a new line
+    pub fn write_error(&self, text: &str) {
+        self.queue.push(SinkMessage::Error(text.to_string()));
+    }
+    // write a line above the prompt without disturbing the input line
+    #[cfg_attr(not(any(feature = "log", feature = "tracing")), allow(dead_code))]
+    pub(crate) fn above_prompt(&self, text: &str) {
+        self.queue.push(SinkMessage::AbovePrompt(text.to_string()));
+    }
+    /// Number of messages thrown away because the queue was full
+    /// # Returns
+    /// * `usize` - the total since the console was created
+    ///
+    pub fn dropped(&self) -> usize {
+        self.queue.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// A command that runs outside of the ui thread
+///
+/// Created by [`ConsoleWindow::begin_command`]. While the session is open the console
+/// ignores typing and shows a spinner. Output written to the [`ConsoleSink`] shows up as
+/// it arrives. [`CommandSession::finish`] ends the command and reprompts. Dropping the
+/// session without finishing it counts as success.
+///
+#[derive(Debug)]
+pub struct CommandSession {
+    sink: ConsoleSink,
+    id: u64,
+    finished: bool,
+}
+
+impl CommandSession {
+    /// Get a sink for writing the command output, it can be cloned and sent to other threads
+    pub fn sink(&self) -> ConsoleSink {
+        self.sink.clone()
+    }
+    /// End the command
+    /// # Arguments
+    /// * `status` - the result of the command, an error message is written to the console
+    ///
+    pub fn finish(mut self, status: Result<(), String>) {
+        self.finished = true;
+        self.sink.queue.push(SinkMessage::Finished(self.id, status));
+    }
+}
+
+impl Drop for CommandSession {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.sink.queue.push(SinkMessage::Finished(self.id, Ok(())));
+        }
+    }
+}
+
+impl ConsoleWindow {
+    /// Get a handle for writing to the console from other threads
+    /// # Returns
+    /// * `ConsoleSink` - a sink that is `Clone + Send + Sync`
+    ///
+    /// All sinks of a console share one queue that is emptied each time the console is
+    /// drawn. See [`crate::ConsoleBuilder::sink_limit`] to bound the queue.
+    ///
+    pub fn sink(&self) -> ConsoleSink {
+        ConsoleSink {
+            queue: self.sink_queue.clone(),
+        }
+    }
+
+    /// Start a command whose output arrives over time
+    /// # Returns
+    /// * `CommandSession` - the session, finish it when the command is done
+    ///
+    /// Call this instead of [`ConsoleWindow::prompt`] after receiving a command. The
+    /// session can be moved to another thread.
+    ///
+    pub fn begin_command(&mut self) -> CommandSession {
+        let id = self.sink_queue.next_session.fetch_add(1, Ordering::Relaxed);
+        self.session = Some(id);
+        CommandSession {
+            sink: self.sink(),
+            id,
+            finished: false,
+        }
+    }
+
+    /// Is a command session running
+    /// # Returns
+    /// * `bool` - true between [`ConsoleWindow::begin_command`] and the end of the session
+    ///
+    pub fn is_busy(&self) -> bool {
+        self.session.is_some()
+    }
+
+    // write everything the sinks have sent since the last frame
+    pub(crate) fn drain_sink(&mut self, ctx: &Context) {
+        self.sink_queue
+            .ctx
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| ctx.clone());
+        let messages = std::mem::take(&mut *self.sink_queue.messages.lock().unwrap());
+        let dropped = self
+            .sink_queue
+            .dropped_unreported
+            .swap(0, Ordering::Relaxed);
+        if dropped > 0 {
+            self.write(&format!("({} lines of output dropped)", dropped));
+        }
+        for msg in messages {
+            match msg {
+                SinkMessage::Print(text) => {
+                    if !self.capture_text(&text) {
+                        self.text.push_str(&text);
+                        self.line_count += text.matches('\n').count();
+                        self.truncate_scroll_back();
+                        self.force_cursor_to_end = true;
+                    }
+                }
+                SinkMessage::Println(text) => self.write(&text),
+                SinkMessage::Error(text) => self.write_error_display(&text),
+                SinkMessage::AbovePrompt(text) => self.write_above_prompt(ctx, &text),
+                SinkMessage::Finished(id, status) => {
+                    if self.session == Some(id) {
+                        self.set_last_status(status.as_ref().map(|_| ()).map_err(|_| 1));
+                    }
+                    if let Err(err) = status {
+                        self.write_error_display(&err);
+                    }
+                    if self.session == Some(id) {
+                        self.session = None;
+                        #[cfg(all(feature = "process", not(target_arch = "wasm32")))]
+                        {
+                            self.process = None;
+                        }
+                        self.prompt();
+                    }
+                }
+            }
+        }
+    }
+
+    // throw away typing while a session is running, ctrl-c kills a running process or
+    // is passed on to the host
+    pub(crate) fn lock_input(&mut self, ui: &Ui) {
+        if self.session.is_none() || !ui.ctx().memory(|mem| mem.has_focus(self.id())) {
+            return;
+        }
+        let has_selection = self.has_selection(ui.ctx());
+        let interrupted = ui.input(|inp| {
+            inp.events.iter().any(|event| match event {
+                Event::Key {
+                    key: egui::Key::C,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } => modifiers.ctrl,
+                Event::Copy => inp.modifiers.ctrl && !has_selection,
+                _ => false,
+            })
+        });
+        if interrupted {
+            #[cfg(all(feature = "process", not(target_arch = "wasm32")))]
+            let killed = self.kill_process();
+            #[cfg(not(all(feature = "process", not(target_arch = "wasm32"))))]
+            let killed = false;
+            if !killed {
+                self.events.push_back(crate::ConsoleEvent::Interrupt);
+            }
+        }
+        ui.ctx().input_mut(|inp| {
+            inp.events.retain(|event| {
+                !matches!(
+                    event,
+                    Event::Text(_)
+                        | Event::Paste(_)
+                        | Event::Cut
+                        | Event::Key { .. }
+                        | Event::Ime(_)
+                )
+            })
+        });
+    }
+
+    // show that a session is running in the bottom right corner
+    pub(crate) fn busy_ui(&self, ui: &Ui, rect: Rect) {
+        if self.session.is_none() {
+            return;
+        }
+        let size = ui.style().spacing.interact_size.y;
+        let spinner_rect = Rect::from_min_size(
+            rect.right_bottom() - egui::vec2(size + 4.0, size + 4.0),
+            egui::vec2(size, size),
+        );
+        egui::Spinner::new().paint_at(ui, spinner_rect);
+    }
+}
+
+#[test]
+fn test_command_session() {
+    let mut console = crate::ConsoleBuilder::new().build();
+    let session = console.begin_command();
+    let sink = session.sink();
+    assert!(console.is_busy());
+
+    let worker = std::thread::spawn(move || {
+        sink.println("line 1");
+        sink.print(" more");
+        sink.write_error("oops");
+        session.finish(Err("failed".to_string()));
+    });
+    worker.join().unwrap();
+
+    console.drain_sink(&Context::default());
+    assert!(!console.is_busy());
+    assert!(console.text.contains("line 1 more\noops\nfailed\n>> "));
+}
+
+#[test]
+fn test_command_session_dropped() {
+    let mut console = crate::ConsoleBuilder::new().build();
+    let session = console.begin_command();
+    session.sink().println("done");
+    drop(session);
+
+    console.drain_sink(&Context::default());
+    assert!(!console.is_busy());
+    assert!(console.text.ends_with("done\n>> "));
+}
+
+#[test]
+fn test_sink_ordering() {
+    let mut console = crate::ConsoleBuilder::new().build();
+    let workers: Vec<_> = (0..4)
+        .map(|w| {
+            let sink = console.sink();
+            std::thread::spawn(move || {
+                for i in 0..100 {
+                    sink.println(&format!("{} {}", w, i));
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    console.drain_sink(&Context::default());
+
+    for w in 0..4 {
+        let seen: Vec<usize> = console
+            .text
+            .lines()
+            .filter_map(|l| l.strip_prefix(&format!("{} ", w)))
+            .map(|i| i.parse().unwrap())
+            .collect();
+        assert_eq!(seen, (0..100).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn test_sink_limit() {
+    let mut console = crate::ConsoleBuilder::new().sink_limit(10).build();
+    let session = console.begin_command();
+    let sink = console.sink();
+    for i in 0..25 {
+        sink.println(&format!("line {}", i));
+    }
+    session.finish(Ok(()));
+    for i in 25..30 {
+        sink.println(&format!("line {}", i));
+    }
+    assert_eq!(sink.dropped(), 21);
+
+    console.drain_sink(&Context::default());
+    // the end of the session is never dropped
+    assert!(!console.is_busy());
+    assert!(console.text.contains("(21 lines of output dropped)"));
+    assert!(!console.text.contains("line 20\n"));
+    assert!(console.text.contains("line 21\n"));
+    assert!(console.text.ends_with("line 29"));
+}
+
+#[test]
+fn test_above_prompt() {
+    let ctx = Context::default();
+    let mut console = crate::ConsoleBuilder::new().build();
+    console.prompt();
+    console.text.push_str("half typed");
+    let sink = console.sink();
+    sink.above_prompt("log 1");
+    sink.above_prompt("log 2");
+    console.drain_sink(&ctx);
+    assert_eq!(console.text, "log 1\nlog 2\n>> half typed");
+    assert_eq!(console.get_last_line(), "half typed");
+
+    // while a command is running there is no prompt to protect
+    let session = console.begin_command();
+    sink.above_prompt("log 3");
+    console.drain_sink(&ctx);
+    assert!(console.text.ends_with(">> half typed\nlog 3"));
+    drop(session);
+}
@@ -1,4 +1,5 @@
 use crate::ConsoleWindow;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
 use itertools::Itertools;
 use std::path::PathBuf;
 
@@ -164,21 +165,16 @@ pub(crate) fn fs_tab_complete(search: &str, nth: usize) -> Option<PathBuf> {
         search_path
     } else {
         // no - look at the parent (ie we got "cd dir/f")
-        let parent = search_path.parent();
-        if parent.is_none() {
-            return None;
+        let p = search_path.parent()?.to_path_buf();
+        // if empty parent then search "." (remember we added the dot so remove it later)
+        if p.display().to_string().is_empty() {
+            added_dot = true;
+            PathBuf::from(dot_slash)
+        } else if p.display().to_string() == "." {
+            // we were given . as a dir
+            PathBuf::from(dot_slash)
         } else {
-            let p = parent.unwrap().to_path_buf();
-            // if empty parent then search "." (remember we added the dot so remove it later)
-            if p.display().to_string().is_empty() {
-                added_dot = true;
-                PathBuf::from(dot_slash)
-            } else if p.display().to_string() == "." {
-                // we were given . as a dir
-                PathBuf::from(dot_slash)
-            } else {
-                p
-            }
+            p
         }
     };
     // convert .. to ../ or ..\
@@ -213,7 +209,7 @@ pub(crate) fn fs_tab_complete(search: &str, nth: usize) -> Option<PathBuf> {
 
         // linux is well-behaved!
         #[cfg(target_os = "linux")]
-        let entries = entries.filter(|e| e.is_ok()).map(|e| e.unwrap());
+        let entries = entries.flatten();
 
         for ent in entries {
             #[cfg(target_arch = "wasm32")]
@@ -0,0 +1,303 @@
+//! A [`tracing_subscriber::Layer`] that writes events into a console
+//!
+//! ```ignore
+//! use tracing_subscriber::prelude::*;
+//! let layer = egui_console::ConsoleLayer::new(console.sink()).with_filter_directives("info,my_app=debug")?;
+//! let filter = layer.filter_handle();
+//! tracing_subscriber::registry().with(layer).init();
+//! // later, from a `log_filter` command
+//! filter.set("my_app::net=trace")?;
+//! ```
+//!
+//! Events are written above the prompt so they never get mixed up with what the user is
+//! typing. They can come from any thread.
+use crate::ConsoleSink;
+use std::{
+    fmt::Write,
+    sync::{Arc, RwLock},
+};
+use tracing::{field::Field, span, Event, Level, Metadata, Subscriber};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+/// A parsed filter like `info,my_app::net=debug,hyper=off`
+///
+/// A bare level sets the default, `target=level` applies to the target and everything
+/// below it, the longest matching target wins.
+///
+#[derive(Debug, Clone, PartialEq)]
+struct Directives {
+    default: Option<Level>,
+    targets: Vec<(String, Option<Level>)>,
+}
+
+impl Directives {
+    fn parse(spec: &str) -> Result<Self, String> {
+        let mut directives = Directives {
+            default: Some(Level::INFO),
+            targets: Vec::new(),
+        };
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            match part.split_once('=') {
+                Some((target, level)) => directives
+                    .targets
+                    .push((target.trim().to_string(), parse_level(level.trim())?)),
+                None => directives.default = parse_level(part)?,
+            }
+        }
+        // longest first so the most specific target is found first
+        directives
+            .targets
+            .sort_by_key(|t| std::cmp::Reverse(t.0.len()));
+        Ok(directives)
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.enabled_at(metadata.target(), metadata.level())
+    }
+
+    fn enabled_at(&self, target: &str, event_level: &Level) -> bool {
+        let level = self
+            .targets
+            .iter()
+            .find(|(t, _)| {
+                target == t
+                    || (target.starts_with(t.as_str()) && target[t.len()..].starts_with("::"))
+            })
+            .map_or(self.default, |(_, level)| *level);
+        // more verbose levels compare greater
+        level.is_some_and(|level| *event_level <= level)
+    }
+}
+
+fn parse_level(level: &str) -> Result<Option<Level>, String> {
+    match level.to_ascii_lowercase().as_str() {
+        "off" => Ok(None),
+        "error" => Ok(Some(Level::ERROR)),
+        "warn" => Ok(Some(Level::WARN)),
+        "info" => Ok(Some(Level::INFO)),
+        "debug" => Ok(Some(Level::DEBUG)),
+        "trace" => Ok(Some(Level::TRACE)),
+        _ => Err(format!("unknown level '{}'", level)),
+    }
+}
+
+/// A handle for changing the filter of a [`ConsoleLayer`] after it is installed
+#[derive(Debug, Clone)]
+pub struct FilterHandle {
+    directives: Arc<RwLock<Directives>>,
+}
+
+impl FilterHandle {
+    /// Replace the filter
+    /// # Arguments
+    /// * `spec` - directives like `info,my_app::net=debug,hyper=off`
+    ///
+    /// # Returns
+    /// * an error naming the bad level, the old filter is kept
+    ///
+    pub fn set(&self, spec: &str) -> Result<(), String> {
+        let directives = Directives::parse(spec)?;
+        *self.directives.write().unwrap() = directives;
+        Ok(())
+    }
+}
+
+/// A [`tracing_subscriber::Layer`] that writes to a [`ConsoleSink`]
+///
+/// Each event becomes one line: the level, the target, the message and then the other
+/// fields as `name=value`. The default filter is `info`.
+///
+#[derive(Debug)]
+pub struct ConsoleLayer {
+    sink: ConsoleSink,
+    directives: Arc<RwLock<Directives>>,
+    spans: bool,
+}
+
+impl ConsoleLayer {
+    /// Create a layer writing to the sink
+    /// # Arguments
+    /// * `sink` - where the events go, see [`crate::ConsoleWindow::sink`]
+    ///
+    pub fn new(sink: ConsoleSink) -> Self {
+        Self {
+            sink,
+            directives: Arc::new(RwLock::new(Directives {
+                default: Some(Level::INFO),
+                targets: Vec::new(),
+            })),
+            spans: false,
+        }
+    }
+    /// Set the filter
+    /// # Arguments
+    /// * `spec` - directives like `info,my_app::net=debug,hyper=off`
+    ///
+    pub fn with_filter_directives(self, spec: &str) -> Result<Self, String> {
+        self.filter_handle().set(spec)?;
+        Ok(self)
+    }
+    /// Also write a line when a span is entered or exited, at TRACE level
+    pub fn with_spans(mut self, spans: bool) -> Self {
+        self.spans = spans;
+        self
+    }
+    /// Get a handle to change the filter later
+    pub fn filter_handle(&self) -> FilterHandle {
+        FilterHandle {
+            directives: self.directives.clone(),
+        }
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.directives.read().unwrap().enabled(metadata)
+    }
+
+    fn span_line<S>(&self, id: &span::Id, ctx: &Context<'_, S>, arrow: &str)
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        if !self.spans {
+            return;
+        }
+        if let Some(span) = ctx.span(id) {
+            let metadata = span.metadata();
+            // span lines are written at TRACE whatever the level of the span
+            if self
+                .directives
+                .read()
+                .unwrap()
+                .enabled_at(metadata.target(), &Level::TRACE)
+            {
+                self.sink.above_prompt(&format!(
+                    "[TRACE] {} {} {}",
+                    metadata.target(),
+                    arrow,
+                    metadata.name()
+                ));
+            }
+        }
+    }
+}
+
+impl<S> Layer<S> for ConsoleLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        // nothing gets formatted for filtered out events
+        if !self.enabled(metadata) {
+            return;
+        }
+        let mut visitor = LineVisitor {
+            line: format!("[{}] {}:", metadata.level(), metadata.target()),
+            fields: String::new(),
+        };
+        event.record(&mut visitor);
+        let mut line = visitor.line;
+        line.push_str(&visitor.fields);
+        self.sink.above_prompt(&line);
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        self.span_line(id, &ctx, "->");
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, S>) {
+        self.span_line(id, &ctx, "<-");
+    }
+}
+
+// flattens the fields of an event into the line, the message goes first
+struct LineVisitor {
+    line: String,
+    fields: String,
+}
+
+impl tracing::field::Visit for LineVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            let _ = write!(self.line, " {}", value);
+        } else {
+            let _ = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.line, " {:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+#[test]
+fn test_directives() {
+    use tracing::callsite::Identifier;
+    use tracing::metadata::Kind;
+    struct Callsite;
+    static CALLSITE: Callsite = Callsite;
+    static META: Metadata<'static> = Metadata::new(
+        "test",
+        "test",
+        Level::TRACE,
+        None,
+        None,
+        None,
+        tracing::field::FieldSet::new(&[], Identifier(&CALLSITE)),
+        Kind::EVENT,
+    );
+    impl tracing::Callsite for Callsite {
+        fn set_interest(&self, _: tracing::subscriber::Interest) {}
+        fn metadata(&self) -> &Metadata<'_> {
+            &META
+        }
+    }
+    let meta = |target: &'static str, level: Level| {
+        Metadata::new(
+            "test",
+            target,
+            level,
+            None,
+            None,
+            None,
+            tracing::field::FieldSet::new(&[], Identifier(&CALLSITE)),
+            Kind::EVENT,
+        )
+    };
+
+    let d = Directives::parse("warn,app::net=trace,hyper=off").unwrap();
+    assert!(d.enabled(&meta("app", Level::WARN)));
+    assert!(!d.enabled(&meta("app", Level::INFO)));
+    assert!(d.enabled(&meta("app::net", Level::TRACE)));
+    assert!(d.enabled(&meta("app::net::tcp", Level::TRACE)));
+    assert!(!d.enabled(&meta("app::network", Level::TRACE)));
+    assert!(!d.enabled(&meta("hyper", Level::ERROR)));
+
+    assert!(Directives::parse("info,app=loud").is_err());
+}
+
+#[test]
+fn test_console_layer() {
+    use tracing_subscriber::prelude::*;
+
+    let mut console = crate::ConsoleBuilder::new().build();
+    console.prompt();
+    let layer = ConsoleLayer::new(console.sink())
+        .with_filter_directives("info")
+        .unwrap();
+    let filter = layer.filter_handle();
+    let subscriber = tracing_subscriber::registry().with(layer);
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info!(target: "app", answer = 42, "hello {}", "world");
+        tracing::debug!(target: "app", "hidden");
+        filter.set("debug").unwrap();
+        tracing::debug!(target: "app", "shown");
+    });
+    console.drain_sink(&egui::Context::default());
+    assert!(console.text.contains("[INFO] app: hello world answer=42\n"));
+    assert!(!console.text.contains("hidden"));
+    assert!(console.text.ends_with("[DEBUG] app: shown\n>> "));
+}
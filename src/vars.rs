@@ -0,0 +1,225 @@
+use crate::ConsoleWindow;
+use std::collections::HashMap;
+
+impl ConsoleWindow {
+    /// Set a variable
+    /// # Arguments
+    /// * `name` - the variable name, letters, digits and underscores
+    /// * `value` - the value
+    ///
+    /// With [`crate::ConsoleBuilder::expand_vars`] on, `$name`, `${name}` and
+    /// `${name:-default}` in an entered line are replaced with the value before aliases are
+    /// expanded. Nothing is replaced inside single quotes, `$$`
+    /// is a literal `$`. `$?` is the exit status of the last command, see
+    /// [`ConsoleWindow::last_status`].
+    ///
+    pub fn set_var(&mut self, name: &str, value: &str) {
+        self.vars.insert(name.to_string(), value.to_string());
+    }
+
+    /// Get a variable
+    /// # Returns
+    /// * `Option<&str>` - the value, if it is set
+    ///
+    pub fn get_var(&self, name: &str) -> Option<&str> {
+        self.vars.get(name).map(String::as_str)
+    }
+
+    /// Remove a variable
+    /// # Returns
+    /// * `Option<String>` - the value it had
+    ///
+    pub fn remove_var(&mut self, name: &str) -> Option<String> {
+        self.vars.remove(name)
+    }
+
+    /// Get the variables
+    /// # Returns
+    /// * `&HashMap<String, String>` - variable names and their values
+    ///
+    pub fn vars(&self) -> &HashMap<String, String> {
+        &self.vars
+    }
+
+    /// Register the `set` and `unset` commands
+    ///
+    /// - `set` lists the variables
+    /// - `set name=value` sets one
+    /// - `unset name` removes one
+    ///
+    pub fn register_var_commands(&mut self) {
+        self.register_command("set", &[], "set or list variables", |console, args| {
+            if args.len() < 2 {
+                let mut vars: Vec<_> = console.vars.iter().collect();
+                vars.sort();
+                let list = vars
+                    .iter()
+                    .map(|(name, value)| format!("{}={}", name, value))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                if !list.is_empty() {
+                    console.write(&list);
+                }
+                return Ok(());
+            }
+            for arg in &args[1..] {
+                match arg.split_once('=') {
+                    Some((name, value)) if is_var_name(name) => console.set_var(name, value),
+                    _ => return Err(format!("set: expected name=value, got '{}'", arg)),
+                }
+            }
+            Ok(())
+        });
+        self.register_command("unset", &[], "remove variables", |console, args| {
+            for name in &args[1..] {
+                console.remove_var(name);
+            }
+            Ok(())
+        });
+    }
+
+    // replace the variables in an entered line, warns about unset ones if asked to
+    pub(crate) fn expand_vars(&mut self, line: &str) -> String {
+        let mut unset = Vec::new();
+        let status = self.last_status.unwrap_or(0);
+        let line = expand(line, &self.vars, status, &mut unset);
+        if self.warn_unset_vars {
+            for name in unset {
+                self.write(&format!("warning: ${} is not set", name));
+            }
+        }
+        line
+    }
+}
+
+fn is_var_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn expand(
+    line: &str,
+    vars: &HashMap<String, String>,
+    status: i32,
+    unset: &mut Vec<String>,
+) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut in_single = false;
+    let mut in_double = false;
+    let mut rest = line;
+
+    while let Some(ch) = rest.chars().next() {
+        rest = &rest[ch.len_utf8()..];
+        match ch {
+            '\'' if !in_double => in_single = !in_single,
+            '"' if !in_single => in_double = !in_double,
+            // keep escaped characters inside double quotes as they are
+            '\\' if in_double => {
+                out.push(ch);
+                if let Some(next) = rest.chars().next() {
+                    out.push(next);
+                    rest = &rest[next.len_utf8()..];
+                }
+                continue;
+            }
+            '$' if !in_single => {
+                if let Some(after) = rest.strip_prefix('$') {
+                    out.push('$');
+                    rest = after;
+                    continue;
+                }
+                // the status of the last command
+                if let Some(after) = rest.strip_prefix('?') {
+                    out.push_str(&status.to_string());
+                    rest = after;
+                    continue;
+                }
+                if let Some((value, after)) = lookup(rest, vars, unset) {
+                    out.push_str(&value);
+                    rest = after;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        out.push(ch);
+    }
+    out
+}
+
+// the value of the variable reference at the start of text and what follows it
+fn lookup<'a>(
+    text: &'a str,
+    vars: &HashMap<String, String>,
+    unset: &mut Vec<String>,
+) -> Option<(String, &'a str)> {
+    let (name, default, after) = if let Some(braced) = text.strip_prefix('{') {
+        let end = braced.find('}')?;
+        let inner = &braced[..end];
+        let (name, default) = match inner.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (inner, None),
+        };
+        (name, default, &braced[end + 1..])
+    } else {
+        let end = text
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(text.len());
+        (&text[..end], None, &text[end..])
+    };
+    if !is_var_name(name) {
+        return None;
+    }
+    let value = match (vars.get(name), default) {
+        (Some(value), _) if !value.is_empty() => value.clone(),
+        (_, Some(default)) => default.to_string(),
+        (Some(value), None) => value.clone(),
+        (None, None) => {
+            unset.push(name.to_string());
+            String::new()
+        }
+    };
+    Some((value, after))
+}
+
+#[test]
+fn test_expand_vars() {
+    let mut console = crate::ConsoleBuilder::new()
+        .expand_vars(true)
+        .warn_unset_vars(true)
+        .build();
+    console.set_var("dir", "/tmp");
+    console.set_var("empty", "");
+    assert_eq!(console.expand_vars("cd $dir/x"), "cd /tmp/x");
+    assert_eq!(console.expand_vars("cd \"${dir}x\""), "cd \"/tmpx\"");
+    assert_eq!(console.expand_vars("echo '$dir'"), "echo '$dir'");
+    assert_eq!(console.expand_vars("echo $$dir $"), "echo $dir $");
+    assert_eq!(console.expand_vars("echo ${nope:-a b}"), "echo a b");
+    assert_eq!(console.expand_vars("echo ${empty:-d}"), "echo d");
+    assert_eq!(
+        console.expand_vars("echo \"it's $dir\""),
+        "echo \"it's /tmp\""
+    );
+    assert!(!console.text.contains("warning"));
+    assert_eq!(console.expand_vars("echo $nope."), "echo .");
+    assert!(console.text.contains("warning: $nope is not set"));
+
+    assert_eq!(console.expand_vars("echo $?"), "echo 0");
+    console.set_last_status(Err(2));
+    assert_eq!(console.expand_vars("echo $?"), "echo 2");
+
+    console.register_var_commands();
+    console.run_registered("set greeting='hello world'".to_string());
+    assert_eq!(console.get_var("greeting"), Some("hello world"));
+    assert_eq!(
+        console.run_registered("echo $greeting".to_string()),
+        crate::ConsoleEvent::Command("echo hello world".to_string())
+    );
+
+    // off by default, the line reaches the host as typed
+    let mut plain = crate::ConsoleBuilder::new().build();
+    plain.set_var("greeting", "hello");
+    assert_eq!(
+        plain.run_registered("echo costs $5 $greeting".to_string()),
+        crate::ConsoleEvent::Command("echo costs $5 $greeting".to_string())
+    );
+}